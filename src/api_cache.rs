@@ -0,0 +1,169 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sha2::Digest;
+
+use crate::errors::MariaDbVmError;
+
+/// Default for how long a cached REST API response is used without revalidation
+pub const DEFAULT_CACHE_TTL_SECS: u64 = 60 * 60;
+
+/// On-disk cache of REST API metadata (release lists, checksums, mirrors) under
+/// `<vm_root>/cache/mariadb-api/`, keyed by URL.
+///
+/// Fresh entries are used as-is; stale ones are revalidated with `ETag`/`Last-Modified`,
+/// and used anyway (with a warning) if the API cannot be reached.
+/// In offline mode only the cache is consulted.
+#[derive(Clone, Debug)]
+pub struct ApiCache {
+    dir: PathBuf,
+    ttl: std::time::Duration,
+    offline: bool,
+}
+
+#[derive(Deserialize, Serialize)]
+struct CacheEntryMetadata {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    fetched_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl ApiCache {
+    pub fn new(vm_root: &std::ffi::OsString, ttl: std::time::Duration, offline: bool) -> Self {
+        ApiCache {
+            dir: Path::new(vm_root.as_os_str())
+                .join("cache")
+                .join("mariadb-api"),
+            ttl,
+            offline,
+        }
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.offline
+    }
+
+    fn entry_paths(&self, url: &reqwest::Url) -> (PathBuf, PathBuf) {
+        let key = hex::encode(sha2::Sha256::digest(url.as_str().as_bytes()));
+        (
+            self.dir.join(format!("{}.json", key)),
+            self.dir.join(format!("{}.body", key)),
+        )
+    }
+
+    fn read_entry(&self, url: &reqwest::Url) -> Option<(CacheEntryMetadata, Vec<u8>)> {
+        let (metadata_file, body_file) = self.entry_paths(url);
+        let metadata: CacheEntryMetadata =
+            serde_json::from_slice(&std::fs::read(metadata_file).ok()?).ok()?;
+        let body = std::fs::read(body_file).ok()?;
+        Some((metadata, body))
+    }
+
+    fn write_entry(
+        &self,
+        url: &reqwest::Url,
+        metadata: &CacheEntryMetadata,
+        body: Option<&[u8]>,
+    ) -> Result<(), MariaDbVmError> {
+        let (metadata_file, body_file) = self.entry_paths(url);
+        std::fs::create_dir_all(&self.dir)?;
+        if let Some(body) = body {
+            std::fs::write(body_file, body)?;
+        }
+        std::fs::write(metadata_file, serde_json::to_vec_pretty(metadata)?)?;
+        Ok(())
+    }
+
+    /// GET `url` as JSON, going through the cache
+    pub async fn get_json<T: serde::de::DeserializeOwned>(
+        &self,
+        client: &reqwest::Client,
+        url: reqwest::Url,
+    ) -> Result<T, MariaDbVmError> {
+        Ok(self.get_json_with_fetched_at(client, url).await?.0)
+    }
+
+    /// GET `url` as JSON, going through the cache, with when it was fetched; see [`Self::get`]
+    pub async fn get_json_with_fetched_at<T: serde::de::DeserializeOwned>(
+        &self,
+        client: &reqwest::Client,
+        url: reqwest::Url,
+    ) -> Result<(T, chrono::DateTime<chrono::Utc>), MariaDbVmError> {
+        let (body, fetched_at) = self.get(client, url).await?;
+        Ok((serde_json::from_slice(&body)?, fetched_at))
+    }
+
+    /// GET `url`, going through the cache. Also returns when the API last served (or, with
+    /// `304 Not Modified`, confirmed) the body, which for a cached or stale one is not now.
+    pub async fn get(
+        &self,
+        client: &reqwest::Client,
+        url: reqwest::Url,
+    ) -> Result<(Vec<u8>, chrono::DateTime<chrono::Utc>), MariaDbVmError> {
+        let cached = self.read_entry(&url);
+        if let Some((metadata, body)) = cached.as_ref() {
+            let age = chrono::Utc::now() - metadata.fetched_at;
+            if self.offline || age.to_std().map_or(true, |age| age < self.ttl) {
+                return Ok((body.clone(), metadata.fetched_at));
+            }
+        } else if self.offline {
+            return Err(MariaDbVmError::OfflineCacheMiss {
+                url: url.to_string(),
+            });
+        }
+
+        let mut request = client.get(url.clone());
+        if let Some((metadata, _)) = cached.as_ref() {
+            if let Some(etag) = &metadata.etag {
+                request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &metadata.last_modified {
+                request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+        eprintln!("GET {}", url);
+        let response = match request.send().await.and_then(|r| r.error_for_status()) {
+            Ok(response) => response,
+            Err(e) => {
+                return match cached {
+                    Some((metadata, body)) => {
+                        eprintln!("Using stale cached response for {}: {}", url, e);
+                        Ok((body, metadata.fetched_at))
+                    }
+                    None => Err(MariaDbVmError::from(e)),
+                }
+            }
+        };
+
+        let header = |name: reqwest::header::HeaderName| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(String::from)
+        };
+        let mut metadata = CacheEntryMetadata {
+            url: url.to_string(),
+            etag: header(reqwest::header::ETAG),
+            last_modified: header(reqwest::header::LAST_MODIFIED),
+            fetched_at: chrono::Utc::now(),
+        };
+
+        match cached {
+            Some((cached_metadata, body))
+                if response.status() == reqwest::StatusCode::NOT_MODIFIED =>
+            {
+                metadata.etag = metadata.etag.or(cached_metadata.etag);
+                metadata.last_modified = metadata.last_modified.or(cached_metadata.last_modified);
+                self.write_entry(&url, &metadata, None)?;
+                Ok((body, metadata.fetched_at))
+            }
+            _ => {
+                let body = response.bytes().await?.to_vec();
+                self.write_entry(&url, &metadata, Some(&body))?;
+                Ok((body, metadata.fetched_at))
+            }
+        }
+    }
+}
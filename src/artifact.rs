@@ -0,0 +1,319 @@
+use crate::errors::MariaDbVmError;
+use crate::mariadb_vm::{Endpoints, Files};
+
+/// Kinds of file MariaDB publishes for a release
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PackageType {
+    /// Binary tarball
+    Bintar,
+    Deb,
+    Rpm,
+    Msi,
+    Zip,
+    Source,
+}
+
+impl PackageType {
+    /// What is downloaded for `os` (a MariaDB `os` label) unless asked otherwise
+    pub fn default_for(os: &str) -> Self {
+        match os {
+            "Windows" => Self::Zip,
+            "Source" => Self::Source,
+            _ => Self::Bintar,
+        }
+    }
+
+    /// Inferred mostly from the filename, as the REST API's `package_type` is free text
+    fn of(file: &Files) -> Option<Self> {
+        let file_name = file.file_name.to_ascii_lowercase();
+        if file.os.as_deref() == Some("Source")
+            || file
+                .package_type
+                .as_deref()
+                .is_some_and(|package_type| package_type.eq_ignore_ascii_case("source"))
+        {
+            Some(Self::Source)
+        } else if file_name.ends_with(".deb") {
+            Some(Self::Deb)
+        } else if file_name.ends_with(".rpm") {
+            Some(Self::Rpm)
+        } else if file_name.ends_with(".msi") {
+            Some(Self::Msi)
+        } else if file_name.ends_with(".zip") {
+            Some(Self::Zip)
+        } else if file_name.ends_with(".tar.gz") || file_name.ends_with(".tgz") {
+            Some(Self::Bintar)
+        } else {
+            None
+        }
+    }
+}
+
+impl std::str::FromStr for PackageType {
+    type Err = MariaDbVmError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "bintar" | "tar.gz" => Ok(Self::Bintar),
+            "deb" => Ok(Self::Deb),
+            "rpm" => Ok(Self::Rpm),
+            "msi" => Ok(Self::Msi),
+            "zip" => Ok(Self::Zip),
+            "source" | "src" => Ok(Self::Source),
+            _ => Err(MariaDbVmError::InvalidPackageType {
+                package_type: s.to_string(),
+            }),
+        }
+    }
+}
+
+impl std::fmt::Display for PackageType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Bintar => "bintar",
+            Self::Deb => "deb",
+            Self::Rpm => "rpm",
+            Self::Msi => "msi",
+            Self::Zip => "zip",
+            Self::Source => "source",
+        })
+    }
+}
+
+/// Build flavours that share an OS, CPU, and package type
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Variant {
+    Systemd,
+    NonSystemd,
+    /// Built against musl rather than glibc
+    Musl,
+    /// Debug builds and debug symbols
+    Debug,
+}
+
+impl std::str::FromStr for Variant {
+    type Err = MariaDbVmError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "systemd" => Ok(Self::Systemd),
+            "non-systemd" => Ok(Self::NonSystemd),
+            "musl" => Ok(Self::Musl),
+            "debug" => Ok(Self::Debug),
+            _ => Err(MariaDbVmError::InvalidVariant {
+                variant: s.to_string(),
+            }),
+        }
+    }
+}
+
+impl std::fmt::Display for Variant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Systemd => "systemd",
+            Self::NonSystemd => "non-systemd",
+            Self::Musl => "musl",
+            Self::Debug => "debug",
+        })
+    }
+}
+
+/// MariaDB's `os` label for a Rust `std::env::consts::OS`
+pub fn mariadb_os(rust_os: &str) -> Option<&'static str> {
+    match rust_os {
+        "linux" => Some("Linux"),
+        "windows" => Some("Windows"),
+        "freebsd" => Some("FreeBSD"),
+        _ => None,
+    }
+}
+
+/// MariaDB's `cpu` label for a Rust architecture name; MariaDB labels pass through
+pub fn mariadb_cpu(rust_arch: &str) -> String {
+    match rust_arch {
+        "x86" | "i386" | "i686" => String::from("x86"),
+        "arm64" => String::from("aarch64"),
+        "powerpc64" => String::from("ppc64"),
+        "powerpc64le" => String::from("ppc64le"),
+        arch => arch.to_ascii_lowercase(),
+    }
+}
+
+/// This machine's architecture for [`mariadb_cpu`]; `std::env::consts::ARCH` is `powerpc64`
+/// for either byte order
+fn host_arch() -> &'static str {
+    match std::env::consts::ARCH {
+        "powerpc64" if cfg!(target_endian = "little") => "powerpc64le",
+        arch => arch,
+    }
+}
+
+/// Which of a release's `files` to download
+#[derive(Clone, Debug)]
+pub struct ArtifactSelector {
+    /// MariaDB `os` label: `Linux`, `Windows`, `FreeBSD`, or `Source`
+    pub os: String,
+    /// MariaDB `cpu` label, e.g., `x86_64`, `aarch64`, `ppc64le`
+    pub cpu: String,
+    pub package_type: PackageType,
+    /// Required flavours. On Linux, systemd builds are preferred when neither
+    /// [`Variant::Systemd`] nor [`Variant::NonSystemd`] is given.
+    pub variants: Vec<Variant>,
+}
+
+impl ArtifactSelector {
+    /// Defaults to this machine's architecture, and its OS' usual package type
+    pub fn new(
+        arch: Option<&str>,
+        package_type: Option<PackageType>,
+        variants: Vec<Variant>,
+    ) -> Result<Self, MariaDbVmError> {
+        let cpu = mariadb_cpu(arch.unwrap_or(host_arch()));
+        // A package type only one OS uses picks that OS, for cross-downloading
+        let os = match (package_type, mariadb_os(std::env::consts::OS)) {
+            (Some(PackageType::Source), _) => "Source",
+            (Some(PackageType::Msi | PackageType::Zip), _) => "Windows",
+            (Some(PackageType::Deb | PackageType::Rpm), _) => "Linux",
+            (Some(PackageType::Bintar), Some("Windows")) => "Linux",
+            (_, Some(os)) => os,
+            (_, None) => {
+                return Err(MariaDbVmError::UnsupportedPlatform {
+                    os: std::env::consts::OS.to_string(),
+                    arch: cpu,
+                })
+            }
+        };
+        Ok(ArtifactSelector {
+            os: os.to_string(),
+            cpu,
+            package_type: package_type.unwrap_or_else(|| PackageType::default_for(os)),
+            variants,
+        })
+    }
+
+    /// The source tarball, which builds anywhere
+    pub fn source() -> Self {
+        ArtifactSelector {
+            os: String::from("Source"),
+            cpu: mariadb_cpu(host_arch()),
+            package_type: PackageType::Source,
+            variants: Vec::with_capacity(0),
+        }
+    }
+
+    fn wants(&self, variant: Variant) -> bool {
+        self.variants.contains(&variant)
+    }
+
+    fn matches(&self, file: &Files) -> bool {
+        let file_name = file.file_name.to_ascii_lowercase();
+        let platform_matches = self.package_type == PackageType::Source
+            || (file
+                .os
+                .as_deref()
+                .is_some_and(|os| os.eq_ignore_ascii_case(&self.os))
+                && file
+                    .cpu
+                    .as_deref()
+                    .is_some_and(|cpu| mariadb_cpu(cpu) == self.cpu));
+        let systemd = file_name.contains("systemd");
+        platform_matches
+            && PackageType::of(file) == Some(self.package_type)
+            && file_name.contains("debug") == self.wants(Variant::Debug)
+            && file_name.contains("musl") == self.wants(Variant::Musl)
+            && (!self.wants(Variant::Systemd) || systemd)
+            && (!self.wants(Variant::NonSystemd) || !systemd)
+    }
+
+    /// The best matching file, if any
+    pub fn select<'a>(&self, files: &'a [Files]) -> Option<&'a Files> {
+        files
+            .iter()
+            .filter(|file| self.matches(file))
+            .max_by_key(|file| file.file_name.to_ascii_lowercase().contains("systemd"))
+    }
+
+    /// Whether `files` has anything at all for this OS. FreeBSD, for one, is never listed.
+    pub fn os_is_listed(&self, files: &[Files]) -> bool {
+        files.iter().any(|file| match self.package_type {
+            PackageType::Source => PackageType::of(file) == Some(PackageType::Source),
+            _ => file
+                .os
+                .as_deref()
+                .is_some_and(|os| os.eq_ignore_ascii_case(&self.os)),
+        })
+    }
+
+    /// Base URL and filename MariaDB conventionally publishes `version` under,
+    /// for when the REST API does not list it
+    pub fn conventional_download(
+        &self,
+        endpoints: &Endpoints,
+        version: &str,
+    ) -> Option<(String, String)> {
+        if self.wants(Variant::Debug) || self.wants(Variant::Musl) {
+            return None;
+        }
+        let release_dir = format!(
+            "{API_BASE}/{version}/",
+            API_BASE = endpoints.api_base,
+            version = version
+        );
+        match (self.os.as_str(), self.package_type) {
+            ("Linux", PackageType::Bintar) => Some((
+                release_dir,
+                format!(
+                    "mariadb-{version}-linux-{systemd}{cpu}.tar.gz",
+                    version = version,
+                    systemd = if self.wants(Variant::NonSystemd) {
+                        ""
+                    } else {
+                        "systemd-"
+                    },
+                    cpu = self.cpu
+                ),
+            )),
+            ("FreeBSD", PackageType::Bintar) => Some((
+                format!(
+                    "{ARCHIVE_BASE}/mariadb-{version}/bintar-freebsd130-{cpu}/",
+                    ARCHIVE_BASE = endpoints.archive_base,
+                    version = version,
+                    cpu = self.cpu
+                ),
+                format!(
+                    "mariadb-{version}-freebsd13.0-{cpu}.tar.gz",
+                    version = version,
+                    cpu = self.cpu
+                ),
+            )),
+            ("Windows", PackageType::Zip | PackageType::Msi) => Some((
+                release_dir,
+                format!(
+                    "mariadb-{version}-win{cpu}.{extension}",
+                    version = version,
+                    cpu = match self.cpu.as_str() {
+                        "x86" => "32",
+                        "x86_64" => "x64",
+                        cpu => cpu,
+                    },
+                    extension = self.package_type
+                ),
+            )),
+            (_, PackageType::Source) => Some((
+                release_dir,
+                format!("mariadb-{version}.tar.gz", version = version),
+            )),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for ArtifactSelector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} {}", self.os, self.cpu, self.package_type)?;
+        for variant in &self.variants {
+            write!(f, " {}", variant)?;
+        }
+        Ok(())
+    }
+}
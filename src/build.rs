@@ -0,0 +1,252 @@
+use std::path::Path;
+
+use sha2::Digest;
+
+use crate::errors::MariaDbVmError;
+use crate::install::{self, InstallManifest};
+
+/// Skips the large storage engines, the embedded server, and the test suites
+const MINIMAL_PRESET: &[&str] = &[
+    "-DPLUGIN_COLUMNSTORE=NO",
+    "-DPLUGIN_CONNECT=NO",
+    "-DPLUGIN_MROONGA=NO",
+    "-DPLUGIN_OQGRAPH=NO",
+    "-DPLUGIN_ROCKSDB=NO",
+    "-DPLUGIN_SPHINX=NO",
+    "-DPLUGIN_SPIDER=NO",
+    "-DPLUGIN_TOKUDB=NO",
+    "-DWITH_EMBEDDED_SERVER=OFF",
+    "-DWITH_UNIT_TESTS=OFF",
+];
+
+/// Named set of CMake options to start a source build from
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BuildPreset {
+    /// MariaDB's own defaults
+    #[default]
+    Default,
+    Minimal,
+}
+
+impl BuildPreset {
+    pub fn cmake_args(&self) -> &'static [&'static str] {
+        match self {
+            Self::Default => &[],
+            Self::Minimal => MINIMAL_PRESET,
+        }
+    }
+}
+
+impl std::str::FromStr for BuildPreset {
+    type Err = MariaDbVmError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "default" => Ok(Self::Default),
+            "minimal" => Ok(Self::Minimal),
+            _ => Err(MariaDbVmError::InvalidBuildPreset {
+                preset: s.to_string(),
+            }),
+        }
+    }
+}
+
+impl std::fmt::Display for BuildPreset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Default => "default",
+            Self::Minimal => "minimal",
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct BuildOptions {
+    pub preset: BuildPreset,
+    /// Extra CMake arguments, e.g., `-DPLUGIN_CONNECT=NO` or `-DWITH_SSL=system`.
+    /// They come after the preset's, so they override it.
+    pub cmake_args: Vec<String>,
+    /// Parallel build jobs; as many as CMake likes when `None`
+    pub jobs: Option<usize>,
+}
+
+impl BuildOptions {
+    /// Every argument `cmake` is configured with, besides the source, build, and install paths
+    pub fn configure_args(&self) -> Vec<String> {
+        std::iter::once("-DCMAKE_BUILD_TYPE=RelWithDebInfo")
+            .chain(self.preset.cmake_args().iter().copied())
+            .map(String::from)
+            .chain(self.cmake_args.iter().cloned())
+            .collect()
+    }
+
+    /// Identifies a build of `version` with these options for this platform and `prefix`,
+    /// which is compiled in, so it is only built once. The job count does not change the
+    /// result, so is not part of it.
+    pub fn cache_key(&self, version: &str, prefix: &Path) -> String {
+        let mut hasher = sha2::Sha256::new();
+        for part in [std::env::consts::OS, std::env::consts::ARCH, version] {
+            hasher.update(part.as_bytes());
+            hasher.update([0]);
+        }
+        hasher.update(prefix.as_os_str().as_encoded_bytes());
+        hasher.update([0]);
+        for arg in self.configure_args() {
+            hasher.update(arg.as_bytes());
+            hasher.update([0]);
+        }
+        format!("{}-{}", version, &hex::encode(hasher.finalize())[..16])
+    }
+}
+
+/// Build the source tarball `source_archive` with CMake for `prefix`, unless this version
+/// was already built with the same options for it, then copy the build into `prefix` and
+/// record the installation under `root`. Builds are staged with `DESTDIR`, and cached,
+/// under `<vm_root>/cache/mariadb-builds/`. As with [`install::install`], `prefix` is only
+/// replaced once the copy is complete, and only if it is empty or was installed before.
+pub fn build_and_install(
+    source_archive: &std::ffi::OsString,
+    version: &str,
+    prefix: &std::ffi::OsString,
+    root: &std::ffi::OsString,
+    vm_root: &std::ffi::OsString,
+    options: &BuildOptions,
+) -> Result<InstallManifest, MariaDbVmError> {
+    let builds_dir = Path::new(vm_root.as_os_str())
+        .join("cache")
+        .join("mariadb-builds");
+    // Compiled into the server (e.g., as its default `basedir`), so has to be absolute
+    let prefix = std::path::absolute(Path::new(prefix.as_os_str()))?;
+    // Before building, which takes a while
+    install::check_replaceable(&prefix, root)?;
+    let key = options.cache_key(version, &prefix);
+    let output_dir = builds_dir.join(&key);
+    // Written last, so an interrupted build is not mistaken for a finished one
+    let complete_marker = builds_dir.join(format!("{}.complete", key));
+
+    if complete_marker.is_file() && output_dir.is_dir() {
+        eprintln!("Using cached build {:?}", output_dir);
+    } else {
+        build(
+            Path::new(source_archive.as_os_str()),
+            &builds_dir.join(format!("{}.work", key)),
+            &output_dir,
+            &prefix,
+            options,
+        )?;
+        std::fs::write(&complete_marker, options.configure_args().join("\n"))?;
+    }
+
+    install::replace_prefix(&prefix, root, |staging| {
+        copy_tree(&staged_prefix(&output_dir, &prefix), staging)
+    })?;
+    let manifest = InstallManifest {
+        version: version.to_string(),
+        prefix,
+        archive: Path::new(source_archive.as_os_str()).to_path_buf(),
+        installed_at: chrono::Utc::now(),
+        build_options: Some(options.configure_args()),
+    };
+    install::record_installed(root, &manifest)?;
+    Ok(manifest)
+}
+
+/// Where `cmake --install` with `DESTDIR=<output_dir>` puts `prefix`: under `output_dir`,
+/// without its root (or drive)
+fn staged_prefix(output_dir: &Path, prefix: &Path) -> std::path::PathBuf {
+    output_dir.join(
+        prefix
+            .components()
+            .filter(|component| {
+                !matches!(
+                    component,
+                    std::path::Component::Prefix(_) | std::path::Component::RootDir
+                )
+            })
+            .collect::<std::path::PathBuf>(),
+    )
+}
+
+fn build(
+    source_archive: &Path,
+    work_dir: &Path,
+    output_dir: &Path,
+    prefix: &Path,
+    options: &BuildOptions,
+) -> Result<(), MariaDbVmError> {
+    // Leftovers of an interrupted build
+    for dir in [work_dir, output_dir] {
+        if dir.is_dir() {
+            std::fs::remove_dir_all(dir)?;
+        }
+    }
+    let source_dir = work_dir.join("source");
+    let build_dir = work_dir.join("build");
+    install::extract_tar_gz_strip_top_level(source_archive, &source_dir)?;
+
+    run(std::process::Command::new("cmake")
+        .arg("-S")
+        .arg(&source_dir)
+        .arg("-B")
+        .arg(&build_dir)
+        .arg(format!("-DCMAKE_INSTALL_PREFIX={}", prefix.display()))
+        .args(options.configure_args()))?;
+
+    let mut build_command = std::process::Command::new("cmake");
+    build_command
+        .arg("--build")
+        .arg(&build_dir)
+        .arg("--parallel");
+    if let Some(jobs) = options.jobs {
+        build_command.arg(jobs.to_string());
+    }
+    run(&mut build_command)?;
+
+    run(std::process::Command::new("cmake")
+        .arg("--install")
+        .arg(&build_dir)
+        .env("DESTDIR", output_dir))?;
+
+    std::fs::remove_dir_all(work_dir)?;
+    Ok(())
+}
+
+fn run(command: &mut std::process::Command) -> Result<(), MariaDbVmError> {
+    eprintln!("{:?}", command);
+    match command.status() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(MariaDbVmError::BuildFailed {
+            command: format!("{:?}", command),
+            details: status.to_string(),
+        }),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(MariaDbVmError::BuildFailed {
+            command: format!("{:?}", command),
+            details: String::from("`cmake` not found on PATH"),
+        }),
+        Err(e) => Err(MariaDbVmError::from(e)),
+    }
+}
+
+/// Recursively copy `from` into `to`, keeping symbolic links as links where supported
+fn copy_tree(from: &Path, to: &Path) -> Result<(), MariaDbVmError> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            copy_tree(&entry.path(), &target)?;
+            continue;
+        }
+        if target.symlink_metadata().is_ok() {
+            std::fs::remove_file(&target)?;
+        }
+        #[cfg(unix)]
+        if file_type.is_symlink() {
+            std::os::unix::fs::symlink(std::fs::read_link(entry.path())?, &target)?;
+            continue;
+        }
+        std::fs::copy(entry.path(), &target)?;
+    }
+    Ok(())
+}
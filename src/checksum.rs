@@ -0,0 +1,132 @@
+use sha2::Digest;
+
+use crate::errors::MariaDbVmError;
+use crate::mariadb_vm::Checksum;
+
+/// Digest algorithms offered by the MariaDB REST API, strongest first
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChecksumAlgorithm {
+    Sha512,
+    Sha256,
+    Sha1,
+    Md5,
+}
+
+impl ChecksumAlgorithm {
+    pub fn is_weak(&self) -> bool {
+        matches!(self, Self::Sha1 | Self::Md5)
+    }
+}
+
+impl std::fmt::Display for ChecksumAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Sha512 => "SHA512",
+            Self::Sha256 => "SHA256",
+            Self::Sha1 => "SHA1",
+            Self::Md5 => "MD5",
+        })
+    }
+}
+
+/// Incremental hasher for whichever algorithm was picked from a [`Checksum`]
+pub enum ChecksumHasher {
+    Sha512(sha2::Sha512),
+    Sha256(sha2::Sha256),
+    Sha1(sha1::Sha1),
+    Md5(md5::Md5),
+}
+
+impl ChecksumHasher {
+    pub fn new(algorithm: ChecksumAlgorithm) -> Self {
+        match algorithm {
+            ChecksumAlgorithm::Sha512 => Self::Sha512(sha2::Sha512::new()),
+            ChecksumAlgorithm::Sha256 => Self::Sha256(sha2::Sha256::new()),
+            ChecksumAlgorithm::Sha1 => Self::Sha1(sha1::Sha1::new()),
+            ChecksumAlgorithm::Md5 => Self::Md5(md5::Md5::new()),
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Sha512(h) => h.update(data),
+            Self::Sha256(h) => h.update(data),
+            Self::Sha1(h) => h.update(data),
+            Self::Md5(h) => h.update(data),
+        }
+    }
+
+    pub fn finalize(self) -> Vec<u8> {
+        match self {
+            Self::Sha512(h) => h.finalize().to_vec(),
+            Self::Sha256(h) => h.finalize().to_vec(),
+            Self::Sha1(h) => h.finalize().to_vec(),
+            Self::Md5(h) => h.finalize().to_vec(),
+        }
+    }
+}
+
+impl std::io::Write for ChecksumHasher {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Checksum {
+    /// Strongest available algorithm and its decoded expected digest.
+    /// Warns when only SHA1 or MD5 is on offer.
+    pub fn strongest(&self) -> Result<(ChecksumAlgorithm, Vec<u8>), MariaDbVmError> {
+        let (algorithm, expected_hex) = [
+            (ChecksumAlgorithm::Sha512, &self.sha512sum),
+            (ChecksumAlgorithm::Sha256, &self.sha256sum),
+            (ChecksumAlgorithm::Sha1, &self.sha1sum),
+            (ChecksumAlgorithm::Md5, &self.md5sum),
+        ]
+        .into_iter()
+        .find_map(
+            |(algorithm, digest)| match digest.as_deref().map(str::trim) {
+                Some(d) if !d.is_empty() => Some((algorithm, d)),
+                _ => None,
+            },
+        )
+        .ok_or(MariaDbVmError::ChecksumUnavailable)?;
+
+        if algorithm.is_weak() {
+            eprintln!(
+                "Warning: only a {} checksum is available, which is not collision resistant",
+                algorithm
+            );
+        }
+        Ok((algorithm, hex::decode(expected_hex)?))
+    }
+
+    /// Compare a finished digest against the expected one
+    pub fn verify_digest(
+        algorithm: ChecksumAlgorithm,
+        expected: &[u8],
+        actual: &[u8],
+    ) -> Result<(), MariaDbVmError> {
+        if expected == actual {
+            Ok(())
+        } else {
+            Err(MariaDbVmError::ChecksumMismatch {
+                algorithm,
+                expected: hex::encode(expected),
+                actual: hex::encode(actual),
+            })
+        }
+    }
+
+    /// Hash the file at `path` with the strongest available algorithm and compare
+    pub fn verify(&self, path: &std::path::Path) -> Result<(), MariaDbVmError> {
+        let (algorithm, expected) = self.strongest()?;
+        let mut hasher = ChecksumHasher::new(algorithm);
+        std::io::copy(&mut std::fs::File::open(path)?, &mut hasher)?;
+        Self::verify_digest(algorithm, &expected, &hasher.finalize())
+    }
+}
@@ -0,0 +1,192 @@
+use std::path::{Path, PathBuf};
+
+use crate::errors::MariaDbVmError;
+use crate::mariadb_vm::{self, MajorReleases};
+use crate::version::MariaDbVersion;
+
+/// Warn this many days ahead of a series' end of life
+pub const DEFAULT_EOL_WARNING_DAYS: u32 = 90;
+
+/// Name of the config file `version_manager_rs` writes for each project
+const PROJECT_CONFIG_FILENAME: &str = "vms-config.json";
+
+/// How deep under each search directory to look for project configs
+const MAX_SCAN_DEPTH: usize = 6;
+
+/// Where a series stands relative to its `release_eol_date`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EolStatus {
+    /// Further than the warning window from its end of life, or without a published date
+    Supported {
+        eol_date: Option<chrono::NaiveDate>,
+    },
+    /// Within the warning window of its end of life
+    NearEol {
+        eol_date: chrono::NaiveDate,
+        days_left: i64,
+    },
+    Eol {
+        eol_date: chrono::NaiveDate,
+    },
+    /// Not in the release list
+    Unknown,
+}
+
+impl std::fmt::Display for EolStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Supported {
+                eol_date: Some(eol_date),
+            } => write!(f, "supported until {}", eol_date),
+            Self::Supported { eol_date: None } => write!(f, "supported, no EOL date published"),
+            Self::NearEol {
+                eol_date,
+                days_left,
+            } => write!(f, "EOL in {} days ({})", days_left, eol_date),
+            Self::Eol { eol_date } => write!(f, "EOL since {}", eol_date),
+            Self::Unknown => write!(f, "EOL unknown"),
+        }
+    }
+}
+
+/// When to warn about, and whether to refuse, versions nearing or past their end of life
+#[derive(Clone, Copy, Debug)]
+pub struct EolPolicy {
+    pub warning_days: u32,
+    /// Fail rather than warn on versions past their end of life, e.g., in CI
+    pub fail_on_eol: bool,
+}
+
+impl Default for EolPolicy {
+    fn default() -> Self {
+        EolPolicy {
+            warning_days: DEFAULT_EOL_WARNING_DAYS,
+            fail_on_eol: false,
+        }
+    }
+}
+
+impl EolPolicy {
+    pub fn status(&self, release: Option<&MajorReleases>, today: chrono::NaiveDate) -> EolStatus {
+        match release {
+            None => EolStatus::Unknown,
+            Some(release) => match release.release_eol_date {
+                Some(eol_date) if eol_date <= today => EolStatus::Eol { eol_date },
+                Some(eol_date) if (eol_date - today).num_days() <= i64::from(self.warning_days) => {
+                    EolStatus::NearEol {
+                        eol_date,
+                        days_left: (eol_date - today).num_days(),
+                    }
+                }
+                eol_date => EolStatus::Supported { eol_date },
+            },
+        }
+    }
+
+    /// Warn on stderr when `version` is past or near its end of life, or not a stable
+    /// release. Past its end of life is an error with [`EolPolicy::fail_on_eol`].
+    pub fn check(
+        &self,
+        version: &str,
+        releases: &[MajorReleases],
+        today: chrono::NaiveDate,
+    ) -> Result<EolStatus, MariaDbVmError> {
+        let release = release_of(version, releases);
+        let status = self.status(release, today);
+        match status {
+            EolStatus::Eol { .. } if self.fail_on_eol => {
+                self.enforce(vec![format!("{} ({})", version, status)])?
+            }
+            EolStatus::Eol { .. } => eprintln!(
+                "Warning: MariaDB {} is {} and no longer gets security fixes; upgrade to a supported series",
+                version, status
+            ),
+            EolStatus::NearEol { .. } => eprintln!(
+                "Warning: MariaDB {} is {}; plan an upgrade to a supported series",
+                version, status
+            ),
+            EolStatus::Supported { .. } | EolStatus::Unknown => {}
+        }
+        if let Some(release) = release {
+            if !release.release_status.eq_ignore_ascii_case("Stable") {
+                eprintln!(
+                    "Warning: MariaDB {} is not a stable release ({}), so not meant for production",
+                    version, release.release_status
+                );
+            }
+        }
+        Ok(status)
+    }
+
+    /// With [`EolPolicy::fail_on_eol`], an error listing the `past_eol` versions, if any
+    pub fn enforce(&self, past_eol: Vec<String>) -> Result<(), MariaDbVmError> {
+        if self.fail_on_eol && !past_eol.is_empty() {
+            return Err(MariaDbVmError::PastEol { versions: past_eol });
+        }
+        Ok(())
+    }
+}
+
+/// The series `version` (e.g., `10.11.7`, `10.11`, or `lts`) belongs to, if it is in `releases`
+pub fn release_of<'a>(version: &str, releases: &'a [MajorReleases]) -> Option<&'a MajorReleases> {
+    let resolved: MariaDbVersion = mariadb_vm::resolve_version(version, releases)
+        .ok()?
+        .parse()
+        .ok()?;
+    mariadb_vm::major_release(&resolved, releases)
+}
+
+/// A project's `vms-config.json`, and the version it selects
+#[derive(Clone, Debug)]
+pub struct ProjectConfig {
+    pub path: PathBuf,
+    pub app_version: String,
+}
+
+/// Every MariaDB project config under `dirs`: each `vms-config.json` with a `"mariadb"`
+/// section, or in a directory named `package_name`, that selects an `app_version`
+pub fn find_project_configs(dirs: &[PathBuf], package_name: &str) -> Vec<ProjectConfig> {
+    let mut found: Vec<PathBuf> = Vec::new();
+    for dir in dirs {
+        if dir.is_file() {
+            found.push(dir.clone());
+        } else {
+            find_config_files(dir, MAX_SCAN_DEPTH, &mut found);
+        }
+    }
+    found.sort();
+    found.dedup();
+    found
+        .into_iter()
+        .filter_map(|path| {
+            let document: serde_json::Value =
+                serde_json::from_slice(&std::fs::read(&path).ok()?).ok()?;
+            let is_mariadb = document.get("mariadb").is_some()
+                || path
+                    .components()
+                    .any(|component| component.as_os_str() == package_name);
+            let app_version = document.get("app_version")?.as_str()?.to_string();
+            is_mariadb.then_some(ProjectConfig { path, app_version })
+        })
+        .collect()
+}
+
+fn find_config_files(dir: &Path, depth: usize, found: &mut Vec<PathBuf>) {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() && depth > 0 => {
+                find_config_files(&entry.path(), depth - 1, found)
+            }
+            Ok(file_type)
+                if file_type.is_file() && entry.file_name() == PROJECT_CONFIG_FILENAME =>
+            {
+                found.push(entry.path())
+            }
+            _ => {}
+        }
+    }
+}
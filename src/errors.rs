@@ -1,7 +1,134 @@
 pub enum MariaDbVmError {
-    Io { source: std::io::Error },
-    Reqwest { source: reqwest::Error },
-    UrlParseError { source: url::ParseError },
+    Io {
+        source: std::io::Error,
+    },
+    Reqwest {
+        source: reqwest::Error,
+    },
+    UrlParseError {
+        source: url::ParseError,
+    },
+    SerdeJson {
+        source: serde_json::Error,
+    },
+    HexDecode {
+        source: hex::FromHexError,
+    },
+    ChecksumUnavailable,
+    ChecksumMismatch {
+        algorithm: crate::checksum::ChecksumAlgorithm,
+        expected: String,
+        actual: String,
+    },
+    SignatureUnavailable {
+        reason: String,
+    },
+    BadSignature {
+        archive: std::path::PathBuf,
+        details: String,
+    },
+    NoMatchingRelease {
+        selector: String,
+    },
+    InvalidVersionSelector {
+        selector: String,
+    },
+    EmptyMirrorList,
+    InvalidMirrorStrategy {
+        strategy: String,
+    },
+    AllMirrorsFailed {
+        failures: Vec<String>,
+    },
+    UnknownMirror {
+        mirror_id: String,
+    },
+    OfflineCacheMiss {
+        url: String,
+    },
+    UnsupportedPlatform {
+        os: String,
+        arch: String,
+    },
+    InvalidPackageType {
+        package_type: String,
+    },
+    InvalidVariant {
+        variant: String,
+    },
+    NoMatchingArtifact {
+        selector: String,
+        available: Vec<String>,
+    },
+    UnmanagedPrefix {
+        prefix: std::path::PathBuf,
+    },
+    InvalidBuildPreset {
+        preset: String,
+    },
+    BuildFailed {
+        command: String,
+        details: String,
+    },
+    InvalidReleaseStatus {
+        status: String,
+    },
+    InvalidSupportType {
+        support: String,
+    },
+    InvalidOutputFormat {
+        format: String,
+        expected: &'static str,
+    },
+    PastEol {
+        versions: Vec<String>,
+    },
+    InvalidRootAuthentication {
+        method: String,
+    },
+    InstallDbNotFound {
+        prefix: std::path::PathBuf,
+    },
+    InitFailed {
+        command: String,
+        details: String,
+    },
+    MyCnfIncludeNotFound {
+        path: std::path::PathBuf,
+    },
+    NotInitialised {
+        data_path: std::path::PathBuf,
+    },
+    ServerStartFailed {
+        details: String,
+    },
+    ServerStopFailed {
+        pid: u32,
+        details: String,
+    },
+    ServerNotRunning {
+        pid_file: std::path::PathBuf,
+    },
+    ServerReloadFailed {
+        pid: u32,
+    },
+    VersionNotInstalled {
+        version: String,
+        root: std::path::PathBuf,
+    },
+    UnitNotTemplatable {
+        version: String,
+        data_path: std::path::PathBuf,
+    },
+    UserUnitDirUnknown,
+    InvalidInstanceName {
+        name: String,
+    },
+    NoFreePort {
+        hostname: String,
+        from: u16,
+        count: u16,
+    },
 }
 
 impl std::fmt::Display for MariaDbVmError {
@@ -10,6 +137,190 @@ impl std::fmt::Display for MariaDbVmError {
             Self::Io { source } => write!(f, "Could not load config: {}", source),
             Self::Reqwest { source } => write!(f, "Download error: {}", source),
             Self::UrlParseError { source } => write!(f, "URL parse error: {}", source),
+            Self::SerdeJson { source } => write!(f, "JSON error: {}", source),
+            Self::HexDecode { source } => write!(f, "Invalid hex digest: {}", source),
+            Self::ChecksumUnavailable => write!(f, "No checksum available to verify against"),
+            Self::ChecksumMismatch {
+                algorithm,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{} verification failed: expected {} but got {}",
+                algorithm, expected, actual
+            ),
+            Self::SignatureUnavailable { reason } => {
+                write!(f, "Signature could not be verified: {}", reason)
+            }
+            Self::BadSignature { archive, details } => {
+                write!(f, "Bad signature on {:?}: {}", archive, details)
+            }
+            Self::NoMatchingRelease { selector } => write!(
+                f,
+                "No release matches {:?}; run `ls-remote` to see which versions are available",
+                selector
+            ),
+            Self::InvalidVersionSelector { selector } => write!(
+                f,
+                "Invalid version {:?}; expected e.g. `latest`, `lts`, `lts-1`, `10.11`, `10.11.x`, `>=10.6 <11`, `~11.4`, or `^10`",
+                selector
+            ),
+            Self::EmptyMirrorList => write!(
+                f,
+                "The mirror list is empty; pick a mirror explicitly with `download --mirror <MIRROR>`"
+            ),
+            Self::InvalidMirrorStrategy { strategy } => write!(
+                f,
+                "Invalid mirror strategy {:?}; expected `random`, `fastest`, or `ordered`",
+                strategy
+            ),
+            Self::AllMirrorsFailed { failures } => write!(
+                f,
+                "Download failed from every mirror tried; pick another with `download --mirror <MIRROR>`:\n  {}",
+                failures.join("\n  ")
+            ),
+            Self::UnknownMirror { mirror_id } => write!(
+                f,
+                "No mirror {:?} in the mirror list; run `mirrors` to see which are available",
+                mirror_id
+            ),
+            Self::OfflineCacheMiss { url } => write!(
+                f,
+                "Offline and {} is not cached; run once without `--offline` to populate the cache",
+                url
+            ),
+            Self::UnsupportedPlatform { os, arch } => write!(
+                f,
+                "No binary release for {} on {}; download the source release with `--package-type source` and build it instead",
+                os, arch
+            ),
+            Self::InvalidPackageType { package_type } => write!(
+                f,
+                "Invalid package type {:?}; expected `bintar`, `deb`, `rpm`, `msi`, `zip`, or `source`",
+                package_type
+            ),
+            Self::InvalidVariant { variant } => write!(
+                f,
+                "Invalid variant {:?}; expected `systemd`, `non-systemd`, `musl`, or `debug`",
+                variant
+            ),
+            Self::NoMatchingArtifact {
+                selector,
+                available,
+            } => write!(
+                f,
+                "No {} file in this release; pick another with `--arch`, `--package-type`, or `--variant` from:\n  {}",
+                selector,
+                available.join("\n  ")
+            ),
+            Self::UnmanagedPrefix { prefix } => write!(
+                f,
+                "{:?} is not empty and was not installed by `install`, so will not be replaced; empty it, or pass another `--bin-path`",
+                prefix
+            ),
+            Self::InvalidBuildPreset { preset } => write!(
+                f,
+                "Invalid build preset {:?}; expected `default` or `minimal`",
+                preset
+            ),
+            Self::BuildFailed { command, details } => write!(
+                f,
+                "Building from source failed: {} ({}); adjust the build with `--build-preset` or `--cmake-arg`",
+                command, details
+            ),
+            Self::InvalidReleaseStatus { status } => write!(
+                f,
+                "Invalid release status {:?}; expected `stable`, `rc`, or `alpha`",
+                status
+            ),
+            Self::InvalidSupportType { support } => write!(
+                f,
+                "Invalid support type {:?}; expected `lts` or `sts`",
+                support
+            ),
+            Self::InvalidOutputFormat { format, expected } => write!(
+                f,
+                "Invalid format {:?}; expected {}",
+                format, expected
+            ),
+            Self::PastEol { versions } => write!(
+                f,
+                "Past end of life, with `--fail-on-eol`; upgrade to a supported series:\n  {}",
+                versions.join("\n  ")
+            ),
+            Self::InvalidRootAuthentication { method } => write!(
+                f,
+                "Invalid root authentication method {:?}; expected `socket` or `normal`",
+                method
+            ),
+            Self::InstallDbNotFound { prefix } => write!(
+                f,
+                "No mariadb-install-db or mysql_install_db under {:?}; run `install` first, or point `--bin-path` at a MariaDB installation",
+                prefix
+            ),
+            Self::InitFailed { command, details } => write!(
+                f,
+                "Initialising the data directory failed: {} ({}); check `--data-path` is empty or remove it, then run `init` again",
+                command, details
+            ),
+            Self::MyCnfIncludeNotFound { path } => write!(
+                f,
+                "No option file {:?} to include in my.cnf; pass an existing file or directory to `--my-cnf-include`",
+                path
+            ),
+            Self::NotInitialised { data_path } => write!(
+                f,
+                "{:?} is not initialised; run `init` first",
+                data_path
+            ),
+            Self::ServerStartFailed { details } => write!(
+                f,
+                "Could not start the server: {}\nCheck the settings it was given with `my-cnf --print`",
+                details
+            ),
+            Self::ServerStopFailed { pid, details } => write!(
+                f,
+                "Could not stop the server, pid {}: {}; stop it by hand with `kill {}`",
+                pid, details, pid
+            ),
+            Self::ServerNotRunning { pid_file } => write!(
+                f,
+                "The server is not running (no live pid in {:?}); start it with `service start`",
+                pid_file
+            ),
+            Self::ServerReloadFailed { pid } => write!(
+                f,
+                "Could not reload the server, pid {}: neither the admin client nor SIGHUP worked",
+                pid
+            ),
+            Self::VersionNotInstalled { version, root } => write!(
+                f,
+                "MariaDB {} is not installed under {:?}; see `ls` for those that are, or install it with `install`",
+                version, root
+            ),
+            Self::UnitNotTemplatable { version, data_path } => write!(
+                f,
+                "Cannot template the unit on version {}: it is not in the data path {:?}, so every instance would share it; use a `--data-path` with the version in it, as the default has, or drop `--template`",
+                version, data_path
+            ),
+            Self::UserUnitDirUnknown => write!(
+                f,
+                "Neither XDG_CONFIG_HOME nor HOME is set, so there is no user unit directory; pass `--service-install-path` without `--user-unit`"
+            ),
+            Self::InvalidInstanceName { name } => write!(
+                f,
+                "Invalid instance name {:?}; use letters, digits, `-`, and `_`",
+                name
+            ),
+            Self::NoFreePort {
+                hostname,
+                from,
+                count,
+            } => write!(
+                f,
+                "No free port on {} among the {} from {}; pass another `--port`",
+                hostname, count, from
+            ),
         }
     }
 }
@@ -20,6 +331,44 @@ impl std::fmt::Debug for MariaDbVmError {
             Self::Io { source } => source.fmt(f),
             Self::Reqwest { source } => source.fmt(f),
             Self::UrlParseError { source } => source.fmt(f),
+            Self::SerdeJson { source } => source.fmt(f),
+            Self::HexDecode { source } => source.fmt(f),
+            Self::ChecksumUnavailable
+            | Self::ChecksumMismatch { .. }
+            | Self::SignatureUnavailable { .. }
+            | Self::BadSignature { .. }
+            | Self::NoMatchingRelease { .. }
+            | Self::InvalidVersionSelector { .. }
+            | Self::EmptyMirrorList
+            | Self::InvalidMirrorStrategy { .. }
+            | Self::AllMirrorsFailed { .. }
+            | Self::UnknownMirror { .. }
+            | Self::OfflineCacheMiss { .. }
+            | Self::UnsupportedPlatform { .. }
+            | Self::InvalidPackageType { .. }
+            | Self::InvalidVariant { .. }
+            | Self::NoMatchingArtifact { .. }
+            | Self::UnmanagedPrefix { .. }
+            | Self::InvalidBuildPreset { .. }
+            | Self::BuildFailed { .. }
+            | Self::InvalidReleaseStatus { .. }
+            | Self::InvalidSupportType { .. }
+            | Self::InvalidOutputFormat { .. }
+            | Self::PastEol { .. }
+            | Self::InvalidRootAuthentication { .. }
+            | Self::InstallDbNotFound { .. }
+            | Self::InitFailed { .. }
+            | Self::MyCnfIncludeNotFound { .. }
+            | Self::NotInitialised { .. }
+            | Self::ServerStartFailed { .. }
+            | Self::ServerStopFailed { .. }
+            | Self::ServerNotRunning { .. }
+            | Self::ServerReloadFailed { .. }
+            | Self::VersionNotInstalled { .. }
+            | Self::UnitNotTemplatable { .. }
+            | Self::UserUnitDirUnknown
+            | Self::InvalidInstanceName { .. }
+            | Self::NoFreePort { .. } => std::fmt::Display::fmt(self, f),
         }
     }
 }
@@ -30,6 +379,44 @@ impl std::error::Error for MariaDbVmError {
             Self::Io { source } => Some(source),
             Self::Reqwest { source } => Some(source),
             Self::UrlParseError { source } => Some(source),
+            Self::SerdeJson { source } => Some(source),
+            Self::HexDecode { source } => Some(source),
+            Self::ChecksumUnavailable
+            | Self::ChecksumMismatch { .. }
+            | Self::SignatureUnavailable { .. }
+            | Self::BadSignature { .. }
+            | Self::NoMatchingRelease { .. }
+            | Self::InvalidVersionSelector { .. }
+            | Self::EmptyMirrorList
+            | Self::InvalidMirrorStrategy { .. }
+            | Self::AllMirrorsFailed { .. }
+            | Self::UnknownMirror { .. }
+            | Self::OfflineCacheMiss { .. }
+            | Self::UnsupportedPlatform { .. }
+            | Self::InvalidPackageType { .. }
+            | Self::InvalidVariant { .. }
+            | Self::NoMatchingArtifact { .. }
+            | Self::UnmanagedPrefix { .. }
+            | Self::InvalidBuildPreset { .. }
+            | Self::BuildFailed { .. }
+            | Self::InvalidReleaseStatus { .. }
+            | Self::InvalidSupportType { .. }
+            | Self::InvalidOutputFormat { .. }
+            | Self::PastEol { .. }
+            | Self::InvalidRootAuthentication { .. }
+            | Self::InstallDbNotFound { .. }
+            | Self::InitFailed { .. }
+            | Self::MyCnfIncludeNotFound { .. }
+            | Self::NotInitialised { .. }
+            | Self::ServerStartFailed { .. }
+            | Self::ServerStopFailed { .. }
+            | Self::ServerNotRunning { .. }
+            | Self::ServerReloadFailed { .. }
+            | Self::VersionNotInstalled { .. }
+            | Self::UnitNotTemplatable { .. }
+            | Self::UserUnitDirUnknown
+            | Self::InvalidInstanceName { .. }
+            | Self::NoFreePort { .. } => None,
         }
     }
 }
@@ -51,3 +438,15 @@ impl From<url::ParseError> for MariaDbVmError {
         Self::UrlParseError { source }
     }
 }
+
+impl From<serde_json::Error> for MariaDbVmError {
+    fn from(source: serde_json::Error) -> Self {
+        Self::SerdeJson { source }
+    }
+}
+
+impl From<hex::FromHexError> for MariaDbVmError {
+    fn from(source: hex::FromHexError) -> Self {
+        Self::HexDecode { source }
+    }
+}
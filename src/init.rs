@@ -0,0 +1,309 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::errors::MariaDbVmError;
+use crate::instance::Instance;
+use crate::my_cnf;
+use crate::server::server_binary;
+use crate::version::MariaDbVersion;
+
+/// Name of the file, in the data directory, that records its initialisation
+const INIT_MANIFEST_FILENAME: &str = "mariadb-vm-init.json";
+
+/// The account `init` creates for clients, e.g., `uri --with-credentials`
+const GENERATED_USER: &str = "mariadb_vm";
+
+/// Of `[A-Za-z0-9]`, so the password needs no quoting in SQL, URLs, or the shell
+const GENERATED_PASSWORD_LENGTH: usize = 24;
+
+/// Where the generated account may connect from: the socket, and TCP on the loopback
+const GENERATED_USER_HOSTS: [&str; 3] = ["localhost", "127.0.0.1", "::1"];
+
+/// How `root@localhost` authenticates; `--auth-root-authentication-method` of `mariadb-install-db`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RootAuthentication {
+    /// As the operating system user of the same name, over the socket
+    #[default]
+    Socket,
+    /// With a password, initially empty
+    Normal,
+}
+
+impl std::str::FromStr for RootAuthentication {
+    type Err = MariaDbVmError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "socket" => Ok(Self::Socket),
+            "normal" => Ok(Self::Normal),
+            _ => Err(MariaDbVmError::InvalidRootAuthentication {
+                method: s.to_string(),
+            }),
+        }
+    }
+}
+
+impl std::fmt::Display for RootAuthentication {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Socket => "socket",
+            Self::Normal => "normal",
+        })
+    }
+}
+
+/// The account `init` created, with every privilege on the database
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct GeneratedCredentials {
+    pub user: String,
+    pub password: String,
+}
+
+impl GeneratedCredentials {
+    fn generate() -> Self {
+        GeneratedCredentials {
+            user: String::from(GENERATED_USER),
+            password: rand::rng()
+                .sample_iter(rand::distr::Alphanumeric)
+                .take(GENERATED_PASSWORD_LENGTH)
+                .map(char::from)
+                .collect(),
+        }
+    }
+}
+
+/// What a data directory was initialised with, as recorded in it
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct InitManifest {
+    pub version: String,
+    pub prefix: PathBuf,
+    pub data_path: PathBuf,
+    pub database: String,
+    pub root_authentication: RootAuthentication,
+    /// None where the system tables were there before `init`, or it predates generating them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credentials: Option<GeneratedCredentials>,
+    pub initialized_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl InitManifest {
+    /// The manifest of `data_path`, if it was initialised by [`init`]
+    pub fn read(data_path: &Path) -> Result<Option<Self>, MariaDbVmError> {
+        let manifest_file = data_path.join(INIT_MANIFEST_FILENAME);
+        if !manifest_file.is_file() {
+            return Ok(None);
+        }
+        let file = std::fs::File::open(manifest_file)?;
+        Ok(Some(serde_json::from_reader(std::io::BufReader::new(
+            file,
+        ))?))
+    }
+
+    /// Readable by its owner only, as it holds the generated password
+    fn write(&self) -> Result<(), MariaDbVmError> {
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let file = options.open(self.data_path.join(INIT_MANIFEST_FILENAME))?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+}
+
+/// Write the managed `my.cnf` of `instance`, create the system tables in its data directory
+/// with `mariadb-install-db` (or `mysql_install_db` before MariaDB 10.4) and an account with a
+/// generated password on them, then the database. Only the database is created in a data
+/// directory that was already initialised, so this is safe to run on every `install`.
+pub fn init(
+    instance: &Instance,
+    root_authentication: RootAuthentication,
+) -> Result<InitManifest, MariaDbVmError> {
+    if my_cnf::write(instance)? {
+        eprintln!("Wrote {:?}", instance.defaults_file);
+    }
+    if let Some(manifest) = InitManifest::read(&instance.data_path)? {
+        println!(
+            "Already initialised {:?} with {} on {}",
+            manifest.data_path,
+            manifest.version,
+            manifest.initialized_at.format("%Y-%m-%d")
+        );
+        create_database(instance)?;
+        return Ok(manifest);
+    }
+    for dir in [
+        &instance.data_path,
+        &instance.runtime_path,
+        &instance.logs_path,
+    ] {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    // The `mysql` schema is what `mariadb-install-db` creates; keep an existing one as is
+    let credentials = if instance.data_path.join("mysql").is_dir() {
+        eprintln!(
+            "{:?} already has system tables, not running install-db or generating credentials",
+            instance.data_path
+        );
+        None
+    } else {
+        let script = install_db_script(&instance.prefix)?;
+        let mut command = std::process::Command::new(&script);
+        command
+            .arg(instance.defaults_file_arg())
+            .arg(format!("--basedir={}", instance.prefix.display()))
+            .arg(format!("--datadir={}", instance.data_path.display()));
+        // Only understood from 10.4 on, which is also when `mysql_install_db` was renamed
+        if supports_auth_root_authentication_method(&instance.version, &script) {
+            command.arg(format!(
+                "--auth-root-authentication-method={}",
+                root_authentication
+            ));
+        }
+        run(&mut command, None)?;
+        Some(create_account(instance)?)
+    };
+
+    create_database(instance)?;
+    let manifest = InitManifest {
+        version: instance.version.clone(),
+        prefix: instance.prefix.clone(),
+        data_path: instance.data_path.clone(),
+        database: instance.database.clone(),
+        root_authentication,
+        credentials,
+        initialized_at: chrono::Utc::now(),
+    };
+    manifest.write()?;
+    println!(
+        "Initialised {:?} with database {:?}",
+        manifest.data_path, manifest.database
+    );
+    Ok(manifest)
+}
+
+/// `scripts/` in binary tarballs, `bin/` in some packages and source builds
+pub(crate) fn install_db_script(prefix: &Path) -> Result<PathBuf, MariaDbVmError> {
+    ["scripts", "bin"]
+        .iter()
+        .flat_map(|dir| {
+            ["mariadb-install-db", "mysql_install_db"]
+                .iter()
+                .map(move |name| prefix.join(dir).join(name))
+        })
+        .find(|script| script.is_file())
+        .ok_or_else(|| MariaDbVmError::InstallDbNotFound {
+            prefix: prefix.to_path_buf(),
+        })
+}
+
+pub(crate) fn supports_auth_root_authentication_method(version: &str, script: &Path) -> bool {
+    match version.parse::<MariaDbVersion>() {
+        Ok(version) => version >= MariaDbVersion::new(10, 4, None),
+        Err(_) => script
+            .file_name()
+            .is_some_and(|name| name == "mariadb-install-db"),
+    }
+}
+
+/// A database is a directory in the data directory, so it can be created without a running
+/// server. Names are encoded the way MariaDB encodes them on disk: `my-db` -> `my@002ddb`.
+fn database_dir(data_path: &Path, database: &str) -> PathBuf {
+    data_path.join(
+        database
+            .chars()
+            .map(|c| match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '_' => c.to_string(),
+                _ => format!("@{:04x}", u32::from(c)),
+            })
+            .collect::<String>(),
+    )
+}
+
+fn create_database(instance: &Instance) -> Result<(), MariaDbVmError> {
+    let dir = database_dir(&instance.data_path, &instance.database);
+    if dir.is_dir() {
+        return Ok(());
+    }
+    std::fs::create_dir_all(&dir)?;
+    let (character_set, collation) = my_cnf::character_set(&instance.locale);
+    std::fs::write(
+        dir.join("db.opt"),
+        format!(
+            "default-character-set={}\ndefault-collation={}\n",
+            character_set, collation
+        ),
+    )?;
+    eprintln!("Created database {:?}", instance.database);
+    Ok(())
+}
+
+/// Create the account of fresh [`GeneratedCredentials`], as `mariadb-install-db` creates
+/// `root`: with the server in bootstrap mode, reading SQL on its stdin. Grant tables are not
+/// loaded in that mode until `FLUSH PRIVILEGES`, and account management needs them.
+fn create_account(instance: &Instance) -> Result<GeneratedCredentials, MariaDbVmError> {
+    let credentials = GeneratedCredentials::generate();
+    let server = server_binary(&instance.prefix).ok_or_else(|| MariaDbVmError::InitFailed {
+        command: String::from("mariadbd --bootstrap"),
+        details: format!(
+            "no mariadbd or mysqld under {:?}",
+            instance.prefix.join("bin")
+        ),
+    })?;
+    let database = instance.database.replace('`', "``");
+    let mut sql = String::from("FLUSH PRIVILEGES;\n");
+    for host in GENERATED_USER_HOSTS {
+        sql.push_str(&format!(
+            "CREATE USER IF NOT EXISTS '{user}'@'{host}' IDENTIFIED BY '{password}';\n\
+             GRANT ALL PRIVILEGES ON `{database}`.* TO '{user}'@'{host}';\n",
+            user = credentials.user,
+            host = host,
+            password = credentials.password,
+            database = database
+        ));
+    }
+    run(
+        std::process::Command::new(server)
+            .arg(instance.defaults_file_arg())
+            .arg("--bootstrap")
+            .arg(format!("--basedir={}", instance.prefix.display()))
+            .arg(format!("--datadir={}", instance.data_path.display())),
+        Some(&sql),
+    )?;
+    eprintln!(
+        "Created user {:?}, with a generated password; see `uri --with-credentials`",
+        credentials.user
+    );
+    Ok(credentials)
+}
+
+/// Run `command` to completion, with `stdin` written to it
+fn run(command: &mut std::process::Command, stdin: Option<&str>) -> Result<(), MariaDbVmError> {
+    eprintln!("{:?}", command);
+    if stdin.is_some() {
+        command.stdin(std::process::Stdio::piped());
+    }
+    let status = command.spawn().and_then(|mut child| {
+        if let (Some(input), Some(mut pipe)) = (stdin, child.stdin.take()) {
+            // Dropped after, so the server sees the end of its input
+            pipe.write_all(input.as_bytes())?;
+        }
+        child.wait()
+    });
+    match status {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(MariaDbVmError::InitFailed {
+            command: format!("{:?}", command),
+            details: status.to_string(),
+        }),
+        Err(e) => Err(MariaDbVmError::InitFailed {
+            command: format!("{:?}", command),
+            details: e.to_string(),
+        }),
+    }
+}
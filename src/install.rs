@@ -0,0 +1,357 @@
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::errors::MariaDbVmError;
+use crate::installed::same_path;
+
+/// Name of the file, under the root, that records every installed version
+const INSTALLED_FILENAME: &str = "installed.json";
+
+/// What was installed where, as recorded in `<root>/installed.json`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct InstallManifest {
+    pub version: String,
+    pub prefix: PathBuf,
+    pub archive: PathBuf,
+    pub installed_at: chrono::DateTime<chrono::Utc>,
+    /// CMake configure arguments, when built from source
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build_options: Option<Vec<String>>,
+}
+
+/// Extract `archive` into `prefix`, stripping the top-level directory of the bintar,
+/// and record the installation under `root`. Whatever was in `prefix` before, e.g., another
+/// release, is replaced, but only once the archive has been extracted in full; see
+/// [`replace_prefix`].
+pub fn install(
+    archive: &std::ffi::OsString,
+    prefix: &std::ffi::OsString,
+    root: &std::ffi::OsString,
+) -> Result<InstallManifest, MariaDbVmError> {
+    let archive = Path::new(archive.as_os_str());
+    let prefix = Path::new(prefix.as_os_str());
+    check_tar_gz(archive)?;
+    let top_level = replace_prefix(prefix, root, |staging| {
+        extract_tar_gz_strip_top_level(archive, staging)
+    })?;
+    let manifest = InstallManifest {
+        version: version_from_top_level_dir(&top_level)
+            .unwrap_or(top_level.as_str())
+            .to_string(),
+        prefix: prefix.to_path_buf(),
+        archive: archive.to_path_buf(),
+        installed_at: chrono::Utc::now(),
+        build_options: None,
+    };
+    record_installed(root, &manifest)?;
+    Ok(manifest)
+}
+
+/// Fill a directory next to `prefix` with `fill`, then swap it in for `prefix`, so a failure
+/// leaves `prefix` as it was. Files a new release does not have would otherwise linger,
+/// and be loaded as plugins. See [`check_replaceable`] for which prefixes are replaced.
+pub(crate) fn replace_prefix<T>(
+    prefix: &Path,
+    root: &std::ffi::OsString,
+    fill: impl FnOnce(&Path) -> Result<T, MariaDbVmError>,
+) -> Result<T, MariaDbVmError> {
+    check_replaceable(prefix, root)?;
+    let staging = sibling(prefix, "new")?;
+    let previous = sibling(prefix, "old")?;
+    // Leftovers of an interrupted install
+    for dir in [&staging, &previous] {
+        if dir.exists() {
+            std::fs::remove_dir_all(dir)?;
+        }
+    }
+    std::fs::create_dir_all(&staging)?;
+    let filled = match fill(&staging) {
+        Ok(filled) => filled,
+        Err(e) => {
+            let _ = std::fs::remove_dir_all(&staging);
+            return Err(e);
+        }
+    };
+
+    if prefix.exists() {
+        std::fs::rename(prefix, &previous)?;
+    }
+    if let Err(e) = std::fs::rename(&staging, prefix) {
+        if previous.exists() {
+            std::fs::rename(&previous, prefix)?;
+        }
+        return Err(MariaDbVmError::from(e));
+    }
+    if previous.exists() {
+        std::fs::remove_dir_all(&previous)?;
+    }
+    Ok(filled)
+}
+
+/// An existing `prefix` is only replaced if it is empty, or `<root>/installed.json` records
+/// it, so `--bin-path /usr/local` is not wiped
+pub(crate) fn check_replaceable(
+    prefix: &Path,
+    root: &std::ffi::OsString,
+) -> Result<(), MariaDbVmError> {
+    let is_empty = std::fs::read_dir(prefix).map_or(true, |mut entries| entries.next().is_none());
+    if !is_empty
+        && !read_installed(root)?
+            .values()
+            .any(|recorded| same_path(&recorded.prefix, prefix))
+    {
+        return Err(MariaDbVmError::UnmanagedPrefix {
+            prefix: prefix.to_path_buf(),
+        });
+    }
+    Ok(())
+}
+
+/// `<parent>/.<name>.<suffix>`: next to `prefix`, so on the same filesystem, and hidden
+fn sibling(prefix: &Path, suffix: &str) -> Result<PathBuf, MariaDbVmError> {
+    let name = prefix.file_name().ok_or_else(|| {
+        MariaDbVmError::from(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("Cannot install into {:?}, which has no name", prefix),
+        ))
+    })?;
+    let mut sibling_name = std::ffi::OsString::from(".");
+    sibling_name.push(name);
+    sibling_name.push(".");
+    sibling_name.push(suffix);
+    Ok(prefix.with_file_name(sibling_name))
+}
+
+/// Add (or replace) `manifest` in `<root>/installed.json`, dropping whatever was installed
+/// into the same prefix before
+pub(crate) fn record_installed(
+    root: &std::ffi::OsString,
+    manifest: &InstallManifest,
+) -> Result<(), MariaDbVmError> {
+    let mut installed = read_installed(root)?;
+    installed.retain(|_, recorded| !same_path(&recorded.prefix, &manifest.prefix));
+    installed.insert(prefix_key(&manifest.prefix), manifest.clone());
+    write_installed(root, &installed)
+}
+
+/// Every installation, keyed by prefix, as one version can be installed into several
+pub fn read_installed(
+    root: &std::ffi::OsString,
+) -> Result<BTreeMap<String, InstallManifest>, MariaDbVmError> {
+    let installed_file = Path::new(root.as_os_str()).join(INSTALLED_FILENAME);
+    if !installed_file.is_file() {
+        return Ok(BTreeMap::new());
+    }
+    let file = std::fs::File::open(installed_file)?;
+    let installed: BTreeMap<String, InstallManifest> =
+        serde_json::from_reader(std::io::BufReader::new(file))?;
+    // Files written before were keyed by version
+    Ok(installed
+        .into_values()
+        .map(|manifest| (prefix_key(&manifest.prefix), manifest))
+        .collect())
+}
+
+fn prefix_key(prefix: &Path) -> String {
+    prefix.to_string_lossy().into_owned()
+}
+
+fn write_installed(
+    root: &std::ffi::OsString,
+    installed: &BTreeMap<String, InstallManifest>,
+) -> Result<(), MariaDbVmError> {
+    let root = Path::new(root.as_os_str());
+    std::fs::create_dir_all(root)?;
+    let file = std::fs::File::create(root.join(INSTALLED_FILENAME))?;
+    serde_json::to_writer_pretty(file, installed)?;
+    Ok(())
+}
+
+/// `mariadb-11.3.2-linux-systemd-x86_64` -> `11.3.2`
+fn version_from_top_level_dir(top_level: &str) -> Option<&str> {
+    top_level
+        .strip_prefix("mariadb-")
+        .and_then(|rest| rest.split('-').next())
+        .filter(|version| !version.is_empty())
+}
+
+/// Only .tar.gz archives can be extracted, which is checked before anything is touched
+pub(crate) fn check_tar_gz(archive: &Path) -> Result<(), MariaDbVmError> {
+    let is_tar_gz = archive
+        .file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.ends_with(".tar.gz") || name.ends_with(".tgz"));
+    if !is_tar_gz {
+        return Err(MariaDbVmError::from(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            format!(
+                "Cannot extract {:?}, only .tar.gz archives are supported",
+                archive
+            ),
+        )));
+    }
+    Ok(())
+}
+
+/// Unpack a .tar.gz into `prefix`, dropping the first path component of every entry.
+/// Returns the name of that (stripped) top-level directory.
+pub(crate) fn extract_tar_gz_strip_top_level(
+    archive: &Path,
+    prefix: &Path,
+) -> Result<String, MariaDbVmError> {
+    check_tar_gz(archive)?;
+    std::fs::create_dir_all(prefix)?;
+    let canonical_prefix = prefix.canonicalize()?;
+    let outside = |entry_path: &Path| {
+        MariaDbVmError::from(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!(
+                "Refusing to extract {:?} outside of {:?}",
+                entry_path, prefix
+            ),
+        ))
+    };
+    let mut top_level: Option<String> = None;
+    let mut tar_archive =
+        tar::Archive::new(flate2::read::GzDecoder::new(std::fs::File::open(archive)?));
+    tar_archive.set_preserve_permissions(true);
+
+    for entry in tar_archive.entries()? {
+        let mut entry = entry?;
+        let entry_path = entry.path()?.into_owned();
+        // Some tarballs are made with `tar -C dir .`, so start `./mariadb-.../`
+        let mut components = without_cur_dir(&entry_path);
+        match components.next() {
+            Some(Component::Normal(first)) => {
+                if top_level.is_none() {
+                    top_level = Some(first.to_string_lossy().into_owned());
+                }
+            }
+            _ => continue,
+        }
+        let stripped = match strip_to_relative(components.as_path()) {
+            Some(p) if p.as_os_str().is_empty() => continue,
+            Some(p) => p,
+            None => return Err(outside(&entry_path)),
+        };
+        // A symlink extracted earlier, e.g., `lib -> /etc`, must not take later entries,
+        // e.g., `lib/passwd`, outside of the prefix
+        if !resolves_within(
+            &canonical_prefix,
+            stripped.parent().unwrap_or(Path::new("")),
+        ) {
+            return Err(outside(&entry_path));
+        }
+        let target = prefix.join(&stripped);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // Unpacking over a symlink would write wherever it points
+        if std::fs::symlink_metadata(&target).is_ok_and(|metadata| !metadata.is_dir()) {
+            std::fs::remove_file(&target)?;
+        }
+
+        if entry.header().entry_type().is_hard_link() {
+            // Hard link targets are relative to the archive root, so strip them too
+            let link_name = entry.link_name()?.map(|l| l.into_owned());
+            let link_target = link_name.as_deref().and_then(|l| {
+                let mut components = without_cur_dir(l);
+                components.next();
+                strip_to_relative(components.as_path())
+            });
+            match link_target {
+                Some(link_target) if resolves_within(&canonical_prefix, &link_target) => {
+                    std::fs::hard_link(prefix.join(link_target), &target)?;
+                }
+                Some(_) => return Err(outside(&entry_path)),
+                None => {
+                    return Err(MariaDbVmError::from(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("Invalid hard link in archive: {:?}", entry_path),
+                    )))
+                }
+            }
+        } else {
+            if entry.header().entry_type().is_symlink() {
+                let link_name = entry.link_name()?.map(|l| l.into_owned());
+                let within = link_name
+                    .as_deref()
+                    .is_some_and(|link_name| symlink_target(&stripped, link_name).is_some());
+                if !within {
+                    return Err(outside(&entry_path));
+                }
+            }
+            entry.unpack(&target)?;
+        }
+    }
+
+    top_level.ok_or_else(|| {
+        MariaDbVmError::from(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("Empty archive: {:?}", archive),
+        ))
+    })
+}
+
+/// The components of `path`, past any leading `.`
+fn without_cur_dir(path: &Path) -> std::path::Components<'_> {
+    let mut components = path.components();
+    while components.clone().next() == Some(Component::CurDir) {
+        components.next();
+    }
+    components
+}
+
+/// Where the symlink at `link` (relative to the prefix) points, if that is within the prefix.
+/// Absolute targets, and `..` beyond the prefix, yield `None`.
+fn symlink_target(link: &Path, target: &Path) -> Option<PathBuf> {
+    let mut out: PathBuf = link.parent().unwrap_or(Path::new("")).to_path_buf();
+    for component in target.components() {
+        match component {
+            Component::Normal(c) => out.push(c),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !out.pop() {
+                    return None;
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(out)
+}
+
+/// Whether `relative`, as far as it exists under `canonical_prefix`, stays inside it once
+/// symlinks are followed
+fn resolves_within(canonical_prefix: &Path, relative: &Path) -> bool {
+    let mut path = canonical_prefix.to_path_buf();
+    for component in relative.components() {
+        path.push(component);
+        match std::fs::symlink_metadata(&path) {
+            Ok(metadata) if metadata.file_type().is_symlink() => match path.canonicalize() {
+                Ok(resolved) if resolved.starts_with(canonical_prefix) => path = resolved,
+                // Outside, or dangling, which creating directories in would follow
+                _ => return false,
+            },
+            Ok(_) => {}
+            // Nothing below here exists yet, so nothing to follow
+            Err(_) => return true,
+        }
+    }
+    true
+}
+
+/// Only allow plain relative paths; anything escaping the prefix yields `None`
+fn strip_to_relative(path: &Path) -> Option<PathBuf> {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(c) => out.push(c),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(out)
+}
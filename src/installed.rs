@@ -0,0 +1,220 @@
+use std::path::{Path, PathBuf};
+
+use crate::errors::MariaDbVmError;
+use crate::install;
+use crate::mariadb_vm::{self, MajorReleases};
+use crate::server::server_binary;
+use crate::version::{MariaDbVersion, VersionReq, VersionSelector};
+
+/// How deep under the root to look for prefixes that are not in `installed.json`
+const MAX_SCAN_DEPTH: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InstallSource {
+    /// Binary archive
+    Download,
+    /// Built from the source tarball
+    Build,
+    /// Found under the root, but not recorded by `install`
+    Unrecorded,
+}
+
+impl std::fmt::Display for InstallSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Download => "download",
+            Self::Build => "build",
+            Self::Unrecorded => "unrecorded",
+        })
+    }
+}
+
+/// An installed MariaDB, from `installed.json` or found on disk
+#[derive(Clone, Debug)]
+pub struct InstalledVersion {
+    pub version: String,
+    pub prefix: PathBuf,
+    pub installed_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub source: InstallSource,
+}
+
+impl InstalledVersion {
+    /// The server binary; `mysqld` before MariaDB 10.5
+    pub fn server_binary(&self) -> Option<PathBuf> {
+        server_binary(&self.prefix)
+    }
+
+    /// The conventional data directory next to the prefix, i.e., `<prefix>/../data`
+    pub fn sibling_data_dir(&self) -> Option<PathBuf> {
+        self.prefix
+            .parent()
+            .map(|parent| parent.join("data"))
+            .filter(|data_dir| data_dir.is_dir())
+    }
+}
+
+/// Everything in `<root>/installed.json`, plus any other prefix under `root` with a server
+/// binary in it, versioned by asking that binary. Sorted by version.
+pub fn list_installed(root: &std::ffi::OsString) -> Result<Vec<InstalledVersion>, MariaDbVmError> {
+    let mut installed: Vec<InstalledVersion> = install::read_installed(root)?
+        .into_values()
+        .map(|manifest| InstalledVersion {
+            version: manifest.version,
+            prefix: manifest.prefix,
+            installed_at: Some(manifest.installed_at),
+            source: match manifest.build_options {
+                Some(_) => InstallSource::Build,
+                None => InstallSource::Download,
+            },
+        })
+        .collect();
+
+    let mut found: Vec<PathBuf> = Vec::new();
+    find_prefixes(Path::new(root.as_os_str()), MAX_SCAN_DEPTH, &mut found);
+    for prefix in found {
+        if installed
+            .iter()
+            .any(|known| same_path(&known.prefix, &prefix))
+        {
+            continue;
+        }
+        if let Some(version) = server_binary(&prefix).and_then(|binary| binary_version(&binary)) {
+            installed.push(InstalledVersion {
+                version,
+                prefix,
+                installed_at: None,
+                source: InstallSource::Unrecorded,
+            });
+        }
+    }
+
+    installed.sort_by(|v0, v1| {
+        match (
+            v0.version.parse::<MariaDbVersion>(),
+            v1.version.parse::<MariaDbVersion>(),
+        ) {
+            (Ok(v0), Ok(v1)) => v0.cmp(&v1),
+            _ => v0.version.cmp(&v1.version),
+        }
+    });
+    Ok(installed)
+}
+
+/// The installation of exactly `version`, as [`list_installed`] finds it: the one in `prefix`,
+/// e.g., `--bin-path`, if that is one, otherwise the last installed
+pub fn find_version(
+    root: &std::ffi::OsString,
+    version: &str,
+    prefix: &Path,
+) -> Result<InstalledVersion, MariaDbVmError> {
+    list_installed(root)?
+        .into_iter()
+        .filter(|installed| installed.version == version)
+        .max_by_key(|installed| (same_path(&installed.prefix, prefix), installed.installed_at))
+        .ok_or_else(|| MariaDbVmError::VersionNotInstalled {
+            version: version.to_string(),
+            root: PathBuf::from(root),
+        })
+}
+
+/// The newest of `installed` (sorted as by [`list_installed`]) that `selector`, e.g.,
+/// `--app-version`, picks. Named selectors (`lts`, `latest`, ...) pick from the series they
+/// resolve to in `releases`.
+pub fn selected<'a>(
+    installed: &'a [InstalledVersion],
+    selector: &str,
+    releases: &[MajorReleases],
+) -> Option<&'a InstalledVersion> {
+    let req = match selector.parse::<VersionSelector>().ok()? {
+        VersionSelector::Range(req) => req,
+        _ => VersionReq::series(
+            &mariadb_vm::resolve_version(selector, releases)
+                .ok()?
+                .parse()
+                .ok()?,
+        ),
+    };
+    installed.iter().rev().find(|installed| {
+        installed
+            .version
+            .parse::<MariaDbVersion>()
+            .is_ok_and(|version| req.matches(&version))
+    })
+}
+
+fn find_prefixes(dir: &Path, depth: usize, found: &mut Vec<PathBuf>) {
+    if server_binary(dir).is_some() {
+        found.push(dir.to_path_buf());
+        return;
+    }
+    if depth == 0 {
+        return;
+    }
+    if let Ok(entries) = std::fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+                find_prefixes(&entry.path(), depth - 1, found);
+            }
+        }
+    }
+}
+
+/// Whether both name the same file, once symlinks are followed
+pub(crate) fn same_path(path0: &Path, path1: &Path) -> bool {
+    match (path0.canonicalize(), path1.canonicalize()) {
+        (Ok(path0), Ok(path1)) => path0 == path1,
+        _ => path0 == path1,
+    }
+}
+
+/// `mariadbd  Ver 11.4.2-MariaDB for Linux on x86_64 (MariaDB Server)` -> `11.4.2`
+pub fn binary_version(server_binary: &Path) -> Option<String> {
+    let output = std::process::Command::new(server_binary)
+        .arg("--version")
+        .output()
+        .ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut words = stdout.split_whitespace();
+    words.find(|word| *word == "Ver")?;
+    words
+        .next()?
+        .split('-')
+        .next()
+        .filter(|version| !version.is_empty())
+        .map(String::from)
+}
+
+/// Processes running a binary from under `prefix`. Only known on Linux, via `/proc`.
+pub fn running_pids(prefix: &Path) -> Option<Vec<u32>> {
+    if std::env::consts::OS != "linux" {
+        return None;
+    }
+    let prefix = prefix.canonicalize().ok()?;
+    Some(
+        std::fs::read_dir("/proc")
+            .ok()?
+            .flatten()
+            .filter_map(|entry| {
+                let pid: u32 = entry.file_name().to_str()?.parse().ok()?;
+                let exe = std::fs::read_link(entry.path().join("exe")).ok()?;
+                exe.starts_with(&prefix).then_some(pid)
+            })
+            .collect(),
+    )
+}
+
+/// Total size of the files under `dir`, not following symbolic links
+pub fn dir_size(dir: &Path) -> u64 {
+    std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| match entry.metadata() {
+                    Ok(metadata) if metadata.is_dir() => dir_size(&entry.path()),
+                    Ok(metadata) => metadata.len(),
+                    Err(_) => 0,
+                })
+                .sum()
+        })
+        .unwrap_or(0)
+}
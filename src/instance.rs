@@ -0,0 +1,202 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::errors::MariaDbVmError;
+
+/// Name of the managed option file, next to the instance's data, run, and logs directories
+const DEFAULTS_FILENAME: &str = "my.cnf";
+
+/// Name of the file, under the root, that records every named instance
+const INSTANCES_FILENAME: &str = "instances.json";
+
+/// How many ports, from the one asked for, to try for a new named instance
+const PORT_RANGE: u16 = 100;
+
+/// One MariaDB server: which installation it runs, where it keeps its files, and how it
+/// is reached. Everything launched for it reads [`Instance::defaults_file`].
+#[derive(Clone, Debug)]
+pub struct Instance {
+    pub version: String,
+    /// Where MariaDB is installed, i.e., `--bin-path`
+    pub prefix: PathBuf,
+    pub data_path: PathBuf,
+    pub runtime_path: PathBuf,
+    pub logs_path: PathBuf,
+    pub hostname: String,
+    pub port: u16,
+    pub database: String,
+    pub locale: String,
+    /// The managed `my.cnf`, see [`crate::my_cnf`]
+    pub defaults_file: PathBuf,
+    /// User-supplied option files (or directories of them) the managed `my.cnf` includes
+    pub includes: Vec<PathBuf>,
+}
+
+impl Instance {
+    pub fn socket(&self) -> PathBuf {
+        self.runtime_path.join("mariadb.sock")
+    }
+
+    pub fn pid_file(&self) -> PathBuf {
+        self.runtime_path.join("mariadbd.pid")
+    }
+
+    pub fn error_log(&self) -> PathBuf {
+        self.logs_path.join("mariadbd.err")
+    }
+
+    /// `--defaults-file=...`, which has to come before any other argument
+    pub fn defaults_file_arg(&self) -> String {
+        format!("--defaults-file={}", self.defaults_file.display())
+    }
+
+    /// This instance on the installation in `prefix`, with the data, run, and logs
+    /// directories and `my.cnf` next to it, as the default layout has them next to `bin`
+    pub fn beside_prefix(self, prefix: PathBuf) -> Self {
+        let dir = prefix.parent().unwrap_or(&prefix).to_path_buf();
+        Instance {
+            data_path: dir.join("data"),
+            runtime_path: dir.join("run"),
+            logs_path: dir.join("logs"),
+            defaults_file: dir.join(DEFAULTS_FILENAME),
+            prefix,
+            ..self
+        }
+    }
+}
+
+/// `<root>/mariadb-version-manager-rs/<app_version>/my.cnf`, alongside the default data,
+/// run, and logs directories (those of `--data-path` and the like)
+pub fn defaults_file_path(root: &std::ffi::OsString, app_version: &str) -> PathBuf {
+    Path::new(root.as_os_str())
+        .join(env!("CARGO_PKG_NAME"))
+        .join(app_version)
+        .join(DEFAULTS_FILENAME)
+}
+
+/// A named instance, e.g., a replica next to the primary of the same version, as recorded in
+/// `<root>/instances.json`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct NamedInstance {
+    pub name: String,
+    /// The version it is an instance of, as started, e.g., `11.4.2`
+    #[serde(alias = "app_version")]
+    pub version: String,
+    pub port: u16,
+    pub data_path: PathBuf,
+    pub runtime_path: PathBuf,
+    pub logs_path: PathBuf,
+    pub defaults_file: PathBuf,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl NamedInstance {
+    /// `instance`, with the directories, port, and `my.cnf` of this named instance
+    pub fn apply(&self, instance: Instance) -> Instance {
+        Instance {
+            data_path: self.data_path.clone(),
+            runtime_path: self.runtime_path.clone(),
+            logs_path: self.logs_path.clone(),
+            port: self.port,
+            defaults_file: self.defaults_file.clone(),
+            ..instance
+        }
+    }
+}
+
+/// `<root>/mariadb-version-manager-rs/<version>/instances/<name>`, next to the default
+/// instance's directories, holding the instance's data, run, and logs directories, and its
+/// `my.cnf`
+pub fn named_instance_dir(root: &std::ffi::OsString, version: &str, name: &str) -> PathBuf {
+    Path::new(root.as_os_str())
+        .join(env!("CARGO_PKG_NAME"))
+        .join(version)
+        .join("instances")
+        .join(name)
+}
+
+/// The instance `name` of `version`. Registered on first use, with its own directories and
+/// the first port after `port`, which is the default instance's, that is not taken by a
+/// running server or another named instance.
+pub fn named_instance(
+    root: &std::ffi::OsString,
+    version: &str,
+    name: &str,
+    hostname: &str,
+    port: u16,
+) -> Result<NamedInstance, MariaDbVmError> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(MariaDbVmError::InvalidInstanceName {
+            name: name.to_string(),
+        });
+    }
+    let mut instances = read_named_instances(root)?;
+    let key = format!("{}/{}", version, name);
+    if let Some(named) = instances.get(&key) {
+        return Ok(named.clone());
+    }
+
+    let taken: Vec<u16> = std::iter::once(port)
+        .chain(instances.values().map(|named| named.port))
+        .collect();
+    let port = (port..=port.saturating_add(PORT_RANGE - 1))
+        .find(|port| !taken.contains(port) && port_is_free(hostname, *port))
+        .ok_or_else(|| MariaDbVmError::NoFreePort {
+            hostname: hostname.to_string(),
+            from: port,
+            count: PORT_RANGE,
+        })?;
+    let dir = named_instance_dir(root, version, name);
+    let named = NamedInstance {
+        name: name.to_string(),
+        version: version.to_string(),
+        port,
+        data_path: dir.join("data"),
+        runtime_path: dir.join("run"),
+        logs_path: dir.join("logs"),
+        defaults_file: dir.join(DEFAULTS_FILENAME),
+        created_at: chrono::Utc::now(),
+    };
+    instances.insert(key, named.clone());
+    write_named_instances(root, &instances)?;
+    // On stderr, as it can come ahead of output meant for scripts, e.g., `my-cnf`
+    eprintln!(
+        "Registered instance {} of {} on port {}",
+        name, version, port
+    );
+    Ok(named)
+}
+
+/// Nothing listens on `port` of `hostname`, if this can listen there
+fn port_is_free(hostname: &str, port: u16) -> bool {
+    std::net::TcpListener::bind((hostname, port)).is_ok()
+}
+
+/// Every named instance, keyed by `<version>/<name>`
+pub fn read_named_instances(
+    root: &std::ffi::OsString,
+) -> Result<BTreeMap<String, NamedInstance>, MariaDbVmError> {
+    let instances_file = Path::new(root.as_os_str()).join(INSTANCES_FILENAME);
+    if !instances_file.is_file() {
+        return Ok(BTreeMap::new());
+    }
+    let file = std::fs::File::open(instances_file)?;
+    Ok(serde_json::from_reader(std::io::BufReader::new(file))?)
+}
+
+fn write_named_instances(
+    root: &std::ffi::OsString,
+    instances: &BTreeMap<String, NamedInstance>,
+) -> Result<(), MariaDbVmError> {
+    let root = Path::new(root.as_os_str());
+    std::fs::create_dir_all(root)?;
+    let file = std::fs::File::create(root.join(INSTANCES_FILENAME))?;
+    serde_json::to_writer_pretty(file, instances)?;
+    Ok(())
+}
@@ -1,2 +1,21 @@
+pub mod api_cache;
+pub mod artifact;
+pub mod build;
+pub mod checksum;
+pub mod eol;
 pub(crate) mod errors;
+pub mod init;
+pub mod install;
+pub mod installed;
+pub mod instance;
+pub mod mariadb_config;
 pub mod mariadb_vm;
+pub mod mirror;
+pub mod my_cnf;
+pub mod openrc;
+pub mod release_list;
+pub mod server;
+pub mod signature;
+pub mod systemd;
+pub mod uri;
+pub mod version;
@@ -1,6 +1,9 @@
 extern crate clap_markdown;
 extern crate version_manager_rs;
-use mariadb_version_manager_rs::mariadb_vm;
+use mariadb_version_manager_rs::{
+    api_cache, artifact, build, eol, init, install, installed, instance, mariadb_config,
+    mariadb_vm, mirror, my_cnf, openrc, release_list, server, signature, systemd, uri,
+};
 
 version_manager_rs::cli_struct_and_helpers!(
     env!("CARGO_PKG_NAME"),
@@ -10,12 +13,450 @@ version_manager_rs::cli_struct_and_helpers!(
     3306_u16 // default port
 );
 
+/// MariaDB-specific options, alongside those every `version_manager_rs` CLI shares
+#[derive(clap::Args, Debug)]
+struct MariaDbArgs {
+    /// OpenPGP keyring to verify archive signatures with. Defaults to the MariaDB release signing key
+    #[arg(long, global = true, env = "MARIADB_VM_KEYRING")]
+    keyring: Option<std::ffi::OsString>,
+
+    /// Refuse to use an archive whose signature could not be verified
+    #[arg(long, global = true, env = "MARIADB_VM_REQUIRE_SIGNATURE")]
+    require_signature: bool,
+
+    /// Download plan (JSON). `download-plan` writes it, `download` executes it without the REST API
+    #[arg(long, global = true)]
+    plan_file: Option<std::ffi::OsString>,
+
+    /// Only use cached REST API responses, never the network
+    #[arg(long, global = true, env = "MARIADB_VM_OFFLINE")]
+    offline: bool,
+
+    /// Seconds a cached REST API response is used before being revalidated
+    #[arg(
+        long,
+        global = true,
+        env = "MARIADB_VM_CACHE_TTL",
+        default_value_t = api_cache::DEFAULT_CACHE_TTL_SECS
+    )]
+    cache_ttl: u64,
+
+    /// Base URL of the MariaDB REST API [default: https://downloads.mariadb.org/rest-api/mariadb]
+    #[arg(long, global = true, env = "MARIADB_VM_API_BASE")]
+    api_base: Option<String>,
+
+    /// Base URL of the MariaDB release archive [default: https://archive.mariadb.org]
+    #[arg(long, global = true, env = "MARIADB_VM_ARCHIVE_BASE")]
+    archive_base: Option<String>,
+
+    /// URL of the mirror list [default: https://downloads.mariadb.org/rest-api/mirrors]
+    #[arg(long, global = true, env = "MARIADB_VM_MIRRORS_URL")]
+    mirrors_url: Option<String>,
+
+    /// How to pick the mirror to download from: random, fastest, or ordered (by `--mirror-preference`)
+    #[arg(
+        long,
+        global = true,
+        env = "MARIADB_VM_MIRROR_STRATEGY",
+        default_value_t = mirror::MirrorStrategy::Random
+    )]
+    mirror_strategy: mirror::MirrorStrategy,
+
+    /// Comma-separated mirror ids or countries, most preferred first, for `--mirror-strategy ordered`
+    #[arg(
+        long,
+        global = true,
+        env = "MARIADB_VM_MIRROR_PREFERENCE",
+        value_delimiter = ','
+    )]
+    mirror_preference: Vec<String>,
+
+    /// Package to download: bintar, deb, rpm, msi, zip, or source. Defaults to zip on Windows, bintar elsewhere
+    #[arg(long, global = true, env = "MARIADB_VM_PACKAGE_TYPE")]
+    package_type: Option<artifact::PackageType>,
+
+    /// CPU architecture to download for, as Rust or MariaDB name it (e.g., x86_64, aarch64, ppc64le). Defaults to this machine's
+    #[arg(long, global = true, env = "MARIADB_VM_ARCH")]
+    arch: Option<String>,
+
+    /// Comma-separated build variants to require: systemd, non-systemd, musl, debug
+    #[arg(long, global = true, env = "MARIADB_VM_VARIANT", value_delimiter = ',')]
+    variant: Vec<artifact::Variant>,
+
+    /// `install` builds the source release with CMake, even where a binary is available
+    #[arg(long, global = true, env = "MARIADB_VM_BUILD_FROM_SOURCE")]
+    build_from_source: bool,
+
+    /// Source build CMake preset: default, or minimal (no large storage engines, embedded server, or tests)
+    #[arg(
+        long,
+        global = true,
+        env = "MARIADB_VM_BUILD_PRESET",
+        default_value_t = build::BuildPreset::Default
+    )]
+    build_preset: build::BuildPreset,
+
+    /// Extra CMake argument for source builds, e.g., -DPLUGIN_CONNECT=NO or -DWITH_SSL=system. Repeatable
+    #[arg(long, global = true, allow_hyphen_values = true)]
+    cmake_arg: Vec<String>,
+
+    /// Parallel jobs for source builds. Defaults to CMake's choice
+    #[arg(long, global = true, env = "MARIADB_VM_BUILD_JOBS")]
+    build_jobs: Option<usize>,
+
+    /// `ls-remote` lists every point release of each series, with its release date and whether there is a binary for `--arch`/`--package-type`/`--variant`
+    #[arg(long, global = true)]
+    point_releases: bool,
+
+    /// `ls-remote` only lists series with this status: stable, rc, or alpha
+    #[arg(long, global = true)]
+    status: Option<release_list::ReleaseStatus>,
+
+    /// `ls-remote` only lists series with this support type: lts or sts
+    #[arg(long, global = true)]
+    support: Option<release_list::SupportType>,
+
+    /// `ls-remote` leaves out series past their end of life
+    #[arg(long, global = true)]
+    not_eol: bool,
+
+    /// Output format of `ls-remote`: table, json, or csv; of `uri`: mysql, mariadb, jdbc, odbc, dsn, sqlx, diesel, or env
+    #[arg(long, global = true, env = "MARIADB_VM_FORMAT")]
+    format: Option<String>,
+
+    /// Warn about versions this many days or fewer from their end of life
+    #[arg(
+        long,
+        global = true,
+        env = "MARIADB_VM_EOL_WARNING_DAYS",
+        default_value_t = eol::DEFAULT_EOL_WARNING_DAYS
+    )]
+    eol_warning_days: u32,
+
+    /// Fail, rather than warn, on versions past their end of life. For CI
+    #[arg(long, global = true, env = "MARIADB_VM_FAIL_ON_EOL")]
+    fail_on_eol: bool,
+
+    /// `install` leaves the data directory alone; run `init` to initialise it later
+    #[arg(long, global = true, env = "MARIADB_VM_NO_INIT")]
+    no_init: bool,
+
+    /// How root@localhost authenticates in a new data directory: socket (as the OS user of that name), or normal (password, initially empty)
+    #[arg(
+        long = "auth-root-authentication-method",
+        value_name = "METHOD",
+        global = true,
+        env = "MARIADB_VM_AUTH_ROOT_AUTHENTICATION_METHOD",
+        default_value_t = init::RootAuthentication::Socket
+    )]
+    root_authentication: init::RootAuthentication,
+
+    /// Option file (or directory of them) for the managed my.cnf to include, for settings of your own. Repeatable
+    #[arg(long, global = true)]
+    my_cnf_include: Vec<std::path::PathBuf>,
+
+    /// Seconds `service start` waits for the server to accept connections, and `service stop` for it to exit
+    #[arg(
+        long,
+        global = true,
+        env = "MARIADB_VM_SERVICE_TIMEOUT",
+        default_value_t = server::DEFAULT_SERVICE_TIMEOUT_SECS
+    )]
+    service_timeout: u64,
+
+    /// `service install systemd` writes a unit for the per-user service manager, to ~/.config/systemd/user
+    #[arg(long, global = true)]
+    user_unit: bool,
+
+    /// `service install systemd` writes the `@.service` template, instantiated per version, e.g., `systemctl start <name>@10.11`
+    #[arg(long, global = true)]
+    template: bool,
+
+    /// Named instance, e.g., replica, with its own data, run, and logs directories under the version's, and its own port: the first free one after `--port`, which the default instance has
+    #[arg(
+        long = "instance",
+        value_name = "NAME",
+        global = true,
+        env = "MARIADB_VM_INSTANCE"
+    )]
+    instance_name: Option<String>,
+
+    /// `uri` includes the user and password `init` generated for the instance
+    #[arg(long, global = true)]
+    with_credentials: bool,
+
+    /// `ls` lists the named instances, with their port, socket, and status, rather than the installed versions
+    #[arg(long, global = true)]
+    instances: bool,
+
+    /// `service install` prints what it would write, rather than writing it
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    /// Set with `mirrors --pin`, kept in the config file
+    #[arg(skip)]
+    pinned_mirror: Option<String>,
+}
+
+/// MariaDB-specific subcommands, alongside those every `version_manager_rs` CLI shares
+#[derive(clap::Subcommand, Debug)]
+enum MariaDbCommands {
+    /// List download mirrors by region, optionally probe them, and pin one as the default
+    Mirrors {
+        /// Measure latency and throughput of each mirror
+        #[arg(long)]
+        probe: bool,
+
+        /// Mirror id for `download` and `install` to use unless `--mirror` is given
+        #[arg(long)]
+        pin: Option<String>,
+
+        /// Forget the pinned mirror
+        #[arg(long, conflicts_with = "pin")]
+        unpin: bool,
+    },
+
+    /// Write the managed my.cnf, which every server and tool launched is given with --defaults-file
+    MyCnf {
+        /// Print its contents rather than its path
+        #[arg(long)]
+        print: bool,
+    },
+
+    /// Initialise the data directory with mariadb-install-db, and create the database. Done by `install` unless `--no-init`
+    Init {},
+
+    /// Check the end of life of every installed version and every project config's version
+    EolReport {
+        /// Directory to look for project configs (vms-config.json) in, besides the vm root. Repeatable
+        #[arg(long)]
+        search: Vec<std::path::PathBuf>,
+    },
+}
+
+/// The version installed at `--bin-path`, as `--app-version` may be e.g. `lts`
+fn installed_version(args: &Cli) -> Result<String, Box<dyn std::error::Error>> {
+    let bin_path = std::path::Path::new(args.bin_path.as_os_str());
+    Ok(installed::list_installed(&args.root)?
+        .into_iter()
+        .find(|installed| installed.prefix == bin_path)
+        .map(|installed| installed.version)
+        .unwrap_or_else(|| args.app_version.clone()))
+}
+
+/// argv for the shared `Cli`, which does not know the [`MariaDbCommands`]: everything before
+/// `subcommand`, with `env` in its place. Arguments after it are MariaDB-specific.
+fn shared_cli_argv(subcommand: &str) -> Vec<std::ffi::OsString> {
+    let mut argv: Vec<std::ffi::OsString> = std::env::args_os()
+        .take_while(|arg| arg != subcommand)
+        .collect();
+    argv.push(std::ffi::OsString::from("env"));
+    argv
+}
+
+impl MariaDbArgs {
+    fn api_cache(&self, vm_root: &std::ffi::OsString) -> api_cache::ApiCache {
+        api_cache::ApiCache::new(
+            vm_root,
+            std::time::Duration::from_secs(self.cache_ttl),
+            self.offline,
+        )
+    }
+
+    /// Fill in whatever was not given on the command line or in the environment
+    /// from the config file
+    fn merge_config(&mut self, config: &mariadb_config::MariaDbConfig) {
+        self.api_base = self.api_base.take().or_else(|| config.api_base.clone());
+        self.archive_base = self
+            .archive_base
+            .take()
+            .or_else(|| config.archive_base.clone());
+        self.mirrors_url = self
+            .mirrors_url
+            .take()
+            .or_else(|| config.mirrors_url.clone());
+        self.pinned_mirror = config.mirror_id.clone();
+        self.my_cnf_include = if self.my_cnf_include.is_empty() {
+            config.my_cnf_include.clone()
+        } else {
+            // Kept in the config file, so relative to where they were given
+            self.my_cnf_include
+                .iter()
+                .map(|include| match std::env::current_dir() {
+                    Ok(cwd) => cwd.join(include),
+                    Err(_) => include.clone(),
+                })
+                .collect()
+        };
+    }
+
+    fn config(&self) -> mariadb_config::MariaDbConfig {
+        mariadb_config::MariaDbConfig {
+            api_base: self.api_base.clone(),
+            archive_base: self.archive_base.clone(),
+            mirrors_url: self.mirrors_url.clone(),
+            mirror_id: self.pinned_mirror.clone(),
+            my_cnf_include: self.my_cnf_include.clone(),
+        }
+    }
+
+    fn endpoints(&self) -> mariadb_vm::Endpoints {
+        mariadb_vm::Endpoints::new(
+            self.api_base.clone(),
+            self.archive_base.clone(),
+            self.mirrors_url.clone(),
+        )
+    }
+
+    fn mirror_options(&self, mirror: &Option<String>) -> mirror::MirrorOptions {
+        mirror::MirrorOptions {
+            mirror: mirror.clone().or_else(|| self.pinned_mirror.clone()),
+            strategy: self.mirror_strategy,
+            preference: self.mirror_preference.clone(),
+        }
+    }
+
+    fn artifact_selector(&self) -> Result<artifact::ArtifactSelector, Box<dyn std::error::Error>> {
+        Ok(artifact::ArtifactSelector::new(
+            self.arch.as_deref(),
+            self.package_type,
+            self.variant.clone(),
+        )?)
+    }
+
+    fn build_options(&self) -> build::BuildOptions {
+        build::BuildOptions {
+            preset: self.build_preset,
+            cmake_args: self.cmake_arg.clone(),
+            jobs: self.build_jobs,
+        }
+    }
+
+    fn release_filter(&self) -> release_list::ReleaseFilter {
+        release_list::ReleaseFilter {
+            status: self.status,
+            support: self.support,
+            not_eol: self.not_eol,
+        }
+    }
+
+    fn eol_policy(&self) -> eol::EolPolicy {
+        eol::EolPolicy {
+            warning_days: self.eol_warning_days,
+            fail_on_eol: self.fail_on_eol,
+        }
+    }
+
+    /// The default instance of `version`, or the named one with `--instance`
+    fn instance(
+        &self,
+        args: &Cli,
+        version: &str,
+    ) -> Result<instance::Instance, Box<dyn std::error::Error>> {
+        self.named(args, self.default_instance(args, version))
+    }
+
+    /// `instance`, or with `--instance`, the named instance of its version
+    fn named(
+        &self,
+        args: &Cli,
+        instance: instance::Instance,
+    ) -> Result<instance::Instance, Box<dyn std::error::Error>> {
+        Ok(match &self.instance_name {
+            Some(name) => instance::named_instance(
+                &args.root,
+                &instance.version,
+                name,
+                &args.hostname,
+                args.port,
+            )?
+            .apply(instance),
+            None => instance,
+        })
+    }
+
+    /// The instance `service start|stop|reload` act on: that of `--bin-path`, or of the
+    /// installation of `version`. Unless that is in `--bin-path`, its data, run, and logs
+    /// directories are those next to it, not `--data-path` and the like, which are another
+    /// version's.
+    fn service_instance(
+        &self,
+        args: &Cli,
+        version: Option<&str>,
+    ) -> Result<instance::Instance, Box<dyn std::error::Error>> {
+        let version = match version {
+            Some(version) => version,
+            None => return self.instance(args, &installed_version(args)?),
+        };
+        let bin_path = std::path::Path::new(args.bin_path.as_os_str());
+        let installed = installed::find_version(&args.root, version, bin_path)?;
+        let instance = self.default_instance(args, &installed.version);
+        self.named(
+            args,
+            if installed.prefix == bin_path {
+                instance
+            } else {
+                instance.beside_prefix(installed.prefix)
+            },
+        )
+    }
+
+    fn default_instance(&self, args: &Cli, version: &str) -> instance::Instance {
+        instance::Instance {
+            version: version.to_string(),
+            prefix: std::path::PathBuf::from(&args.bin_path),
+            data_path: std::path::PathBuf::from(&args.data_path),
+            runtime_path: std::path::PathBuf::from(&args.runtime_path),
+            logs_path: std::path::PathBuf::from(&args.logs_path),
+            hostname: args.hostname.clone(),
+            port: args.port,
+            database: args.database.clone(),
+            locale: args.locale.clone(),
+            defaults_file: instance::defaults_file_path(&args.root, &args.app_version),
+            includes: self.my_cnf_include.clone(),
+        }
+    }
+
+    fn signature_options(&self) -> signature::SignatureOptions {
+        signature::SignatureOptions {
+            keyring: self.keyring.clone(),
+            require_signature: self.require_signature,
+        }
+    }
+}
+
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
+async fn main() -> std::process::ExitCode {
+    match run().await {
+        Ok(()) => std::process::ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    let cli_command = <MariaDbCommands as clap::Subcommand>::augment_subcommands(
+        <MariaDbArgs as clap::Args>::augment_args(<Cli as clap::CommandFactory>::command()),
+    );
+    let matches = cli_command.clone().get_matches();
+    let mut mariadb_args = <MariaDbArgs as clap::FromArgMatches>::from_arg_matches(&matches)?;
+    let mariadb_command: Option<MariaDbCommands> = match matches.subcommand_name() {
+        Some(name) if <MariaDbCommands as clap::Subcommand>::has_subcommand(name) => Some(
+            <MariaDbCommands as clap::FromArgMatches>::from_arg_matches(&matches)?,
+        ),
+        _ => None,
+    };
     let args = {
-        let mut _args = Cli::parse();
+        let mut _args = match (&mariadb_command, matches.subcommand_name()) {
+            (Some(_), Some(name)) => <Cli as clap::FromArgMatches>::from_arg_matches(
+                &cli_command.clone().get_matches_from(shared_cli_argv(name)),
+            )?,
+            _ => <Cli as clap::FromArgMatches>::from_arg_matches(&matches)?,
+        };
         if _args.markdown_help {
-            clap_markdown::print_help_markdown::<Cli>();
+            println!("{}", clap_markdown::help_markdown_command(&cli_command));
             return Ok(());
         }
         config::maybe_config_from_file(&mut _args)?.unwrap_or_else(|| {
@@ -23,38 +464,646 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             _args
         })
     };
-    println!("args.root: {:?}\n", args.root);
+    mariadb_args.merge_config(&mariadb_config::MariaDbConfig::read(&args.vms_config)?);
+
+    let api_cache = mariadb_args.api_cache(&args.vm_root);
+    let endpoints = mariadb_args.endpoints();
 
+    // Set when the release list did not come straight from the REST API
+    let mut offline_releases_origin: Option<(chrono::DateTime<chrono::Utc>, String)> = None;
     let releases: Vec<mariadb_vm::MajorReleases> = match &args.command {
-        Commands::Ls {} | Commands::Env {} => Vec::with_capacity(0),
-        _ => match std::env::var("SKIP_REMOTE_VERSION_REFRESH") {
-            Ok(_) => mariadb_vm::offline_major_releases(),
-            Err(_) => mariadb_vm::versions_from_remote().await?,
-        },
+        _ if matches!(
+            mariadb_command,
+            Some(
+                MariaDbCommands::Mirrors { probe: false, .. }
+                    | MariaDbCommands::MyCnf { .. }
+                    | MariaDbCommands::Init {}
+            )
+        ) =>
+        {
+            Vec::with_capacity(0)
+        }
+        Commands::Ls {}
+        | Commands::Env {}
+        | Commands::Uri {}
+        | Commands::Service(
+            ServiceCommands::Stop { .. }
+            | ServiceCommands::Reload { .. }
+            | ServiceCommands::Install(..),
+        ) if mariadb_command.is_none() => Vec::with_capacity(0),
+        Commands::Download { .. } if mariadb_args.plan_file.is_some() => Vec::with_capacity(0),
+        _ => {
+            let remote = match std::env::var("SKIP_REMOTE_VERSION_REFRESH") {
+                Ok(_) => None,
+                Err(_) => {
+                    match mariadb_vm::versions_from_remote(&args.vm_root, &api_cache, &endpoints)
+                        .await
+                    {
+                        Ok(releases) => Some(releases),
+                        Err(e) => {
+                            eprintln!("Could not refresh release list, using offline copy: {}", e);
+                            None
+                        }
+                    }
+                }
+            };
+            match remote {
+                Some(releases) => releases,
+                None => {
+                    let (snapshot, cache_file) = mariadb_vm::offline_major_releases(&args.vm_root)?;
+                    offline_releases_origin = Some((
+                        snapshot.fetched_at,
+                        match cache_file {
+                            Some(cache_file) => format!("{}", cache_file.display()),
+                            None => String::from("snapshot embedded at build time"),
+                        },
+                    ));
+                    snapshot.major_releases
+                }
+            }
+        }
     };
 
     match &args.command {
+        _ if mariadb_command.is_some() => match mariadb_command.as_ref().unwrap() {
+            MariaDbCommands::Mirrors { probe, pin, unpin } => {
+                let client = mariadb_vm::http_client()?;
+                let mirror_list = mariadb_vm::list_mirrors(&client, &api_cache, &endpoints).await?;
+                if pin.is_some() || *unpin {
+                    if let Some(mirror_id) = pin {
+                        mirror::find_mirror(&mirror_list, mirror_id)?;
+                    }
+                    // Only the pin changes; other settings given for this run are not persisted
+                    let mut config = mariadb_config::MariaDbConfig::read(&args.vms_config)?;
+                    config.mirror_id = pin.clone();
+                    config.write(&args.vms_config)?;
+                    mariadb_args.pinned_mirror = pin.clone();
+                    match pin {
+                        Some(mirror_id) => {
+                            println!("Pinned mirror {} in {:?}", mirror_id, args.vms_config)
+                        }
+                        None => println!("Unpinned mirror in {:?}", args.vms_config),
+                    }
+                }
+
+                let mut probes: std::collections::HashMap<
+                    String,
+                    (Option<std::time::Duration>, Option<f64>),
+                > = std::collections::HashMap::new();
+                if *probe {
+                    // Any file will do; the newest release is the one most likely on every mirror
+                    let release_id: String = mariadb_vm::resolve_point_release(
+                        "latest", &releases, &api_cache, &endpoints,
+                    )
+                    .await?;
+                    let plan = mariadb_vm::download_plan(
+                        &release_id,
+                        &mariadb_args.artifact_selector()?,
+                        &mirror::MirrorOptions::default(),
+                        &api_cache,
+                        &endpoints,
+                    )
+                    .await?;
+                    match plan.file_id {
+                        Some(file_id) => {
+                            let mirror_url = |mirror_id: &str| {
+                                mariadb_vm::mirror_download_url(
+                                    &endpoints,
+                                    &plan.version,
+                                    file_id,
+                                    mirror_id,
+                                )
+                            };
+                            let mirror_ids: Vec<String> = mirror_list
+                                .mirror_list
+                                .values()
+                                .flatten()
+                                .map(|mirror| mirror.mirror_id.clone())
+                                .collect();
+                            for (mirror_id, latency) in
+                                mirror::probe_mirrors(&client, &mirror_ids, &mirror_url).await?
+                            {
+                                let throughput = match latency {
+                                    Some(_) => {
+                                        mirror::measure_throughput(&client, mirror_url(&mirror_id)?)
+                                            .await
+                                    }
+                                    None => None,
+                                };
+                                probes.insert(mirror_id, (latency, throughput));
+                            }
+                        }
+                        None => eprintln!(
+                            "No downloadable file for {} on this platform to probe mirrors with",
+                            plan.version
+                        ),
+                    }
+                }
+
+                for (region, mirrors) in mirror::regions(&mirror_list) {
+                    println!("{}", region);
+                    for mirror in mirrors {
+                        let pinned = mariadb_args.pinned_mirror.as_ref() == Some(&mirror.mirror_id);
+                        let probed = match probes.get(&mirror.mirror_id) {
+                            Some((Some(latency), throughput)) => format!(
+                                "\t{} ms\t{}",
+                                latency.as_millis(),
+                                match throughput {
+                                    Some(bytes_per_sec) => {
+                                        format!("{:.0} KiB/s", bytes_per_sec / 1024.0)
+                                    }
+                                    None => String::from("? KiB/s"),
+                                }
+                            ),
+                            Some((None, _)) => String::from("\tunreachable"),
+                            None => String::new(),
+                        };
+                        println!(
+                            "{} {}\t{}{}",
+                            if pinned { "*" } else { " " },
+                            mirror.mirror_id,
+                            mirror.mirror_name,
+                            probed
+                        );
+                    }
+                }
+            }
+            MariaDbCommands::MyCnf { print } => {
+                let instance = mariadb_args.instance(&args, &installed_version(&args)?)?;
+                my_cnf::write(&instance)?;
+                if *print {
+                    print!("{}", my_cnf::render(&instance))
+                } else {
+                    println!("{}", instance.defaults_file.display())
+                }
+            }
+            MariaDbCommands::Init {} => {
+                init::init(
+                    &mariadb_args.instance(&args, &installed_version(&args)?)?,
+                    mariadb_args.root_authentication,
+                )?;
+            }
+            MariaDbCommands::EolReport { search } => {
+                let today = chrono::Utc::now().date_naive();
+                let eol_policy = mariadb_args.eol_policy();
+                let mut past_eol: Vec<String> = Vec::new();
+                let mut report = |kind: &str, version: &str, location: &std::path::Path| {
+                    let status = eol_policy.status(eol::release_of(version, &releases), today);
+                    if let eol::EolStatus::Eol { .. } = status {
+                        past_eol.push(format!(
+                            "{} ({}) in {}",
+                            version,
+                            status,
+                            location.display()
+                        ));
+                    }
+                    println!("{}\t{}\t{}\t{}", kind, version, status, location.display());
+                };
+                for installed in installed::list_installed(&args.root)? {
+                    report("installed", &installed.version, &installed.prefix);
+                }
+                let search_dirs: Vec<std::path::PathBuf> = [
+                    std::path::PathBuf::from(&args.vm_root),
+                    std::path::PathBuf::from(&args.vms_config),
+                ]
+                .into_iter()
+                .chain(search.iter().cloned())
+                .collect();
+                for project_config in
+                    eol::find_project_configs(&search_dirs, env!("CARGO_PKG_NAME"))
+                {
+                    report("config", &project_config.app_version, &project_config.path);
+                }
+                eol_policy.enforce(past_eol)?;
+            }
+        },
+        Commands::Download { .. } if mariadb_args.plan_file.is_some() => {
+            let plan: mariadb_vm::DownloadPlan = serde_json::from_reader(std::fs::File::open(
+                mariadb_args.plan_file.as_ref().unwrap(),
+            )?)?;
+            if let Some(filepath) = mariadb_vm::download_from_plan(
+                &plan,
+                &args.vm_root,
+                false,
+                &mariadb_args.signature_options(),
+            )
+            .await?
+            {
+                println!("Downloaded: {:?}", filepath)
+            }
+        }
         Commands::Download { version, mirror } => {
-            let release_id: String = mariadb_vm::resolve_version(
+            let release_id: String = mariadb_vm::resolve_point_release(
                 match version {
                     Some(v) => v,
                     None => "latest",
                 },
                 &releases,
-            );
-            match mariadb_vm::download(&release_id, &args.vm_root, false, mirror).await? {
-                Some(filepath) => println!("Downloaded: {:?}", filepath),
-                None => {}
+                &api_cache,
+                &endpoints,
+            )
+            .await?;
+            if let Some(filepath) = mariadb_vm::download(
+                &release_id,
+                &args.vm_root,
+                false,
+                &mariadb_args.artifact_selector()?,
+                &mariadb_args.mirror_options(mirror),
+                &mariadb_args.signature_options(),
+                &api_cache,
+                &endpoints,
+            )
+            .await?
+            {
+                println!("Downloaded: {:?}", filepath)
+            }
+        }
+        Commands::DownloadPlan { version } => {
+            let release_id: String = mariadb_vm::resolve_point_release(
+                match version {
+                    Some(v) => v,
+                    None => "latest",
+                },
+                &releases,
+                &api_cache,
+                &endpoints,
+            )
+            .await?;
+            let plan = mariadb_vm::download_plan(
+                &release_id,
+                &mariadb_args.artifact_selector()?,
+                &mariadb_args.mirror_options(&None),
+                &api_cache,
+                &endpoints,
+            )
+            .await?;
+            match &mariadb_args.plan_file {
+                Some(plan_file) => {
+                    serde_json::to_writer_pretty(std::fs::File::create(plan_file)?, &plan)?;
+                    println!(
+                        "Wrote download plan for {} to {:?}",
+                        plan.version, plan_file
+                    )
+                }
+                None => println!("{}", serde_json::to_string_pretty(&plan)?),
+            }
+        }
+        Commands::Install {
+            version,
+            skip_dependencies: _,
+        } => {
+            let release_id: String = mariadb_vm::resolve_point_release(
+                match version {
+                    Some(v) => v,
+                    None => args.app_version.as_str(),
+                },
+                &releases,
+                &api_cache,
+                &endpoints,
+            )
+            .await?;
+            mariadb_args.eol_policy().check(
+                &release_id,
+                &releases,
+                chrono::Utc::now().date_naive(),
+            )?;
+            let selector = match mariadb_args.artifact_selector() {
+                Ok(_) if mariadb_args.build_from_source => artifact::ArtifactSelector::source(),
+                Ok(selector) => selector,
+                Err(e) => {
+                    eprintln!("{}\nBuilding from source instead", e);
+                    artifact::ArtifactSelector::source()
+                }
+            };
+            let plan = mariadb_vm::download_plan_or_source(
+                &release_id,
+                &selector,
+                &mariadb_args.mirror_options(&None),
+                &api_cache,
+                &endpoints,
+            )
+            .await?;
+            if let Some(filepath) = mariadb_vm::download_from_plan(
+                &plan,
+                &args.vm_root,
+                false,
+                &mariadb_args.signature_options(),
+            )
+            .await?
+            {
+                let manifest = if plan.is_source() {
+                    build::build_and_install(
+                        &filepath,
+                        &plan.version,
+                        &args.bin_path,
+                        &args.root,
+                        &args.vm_root,
+                        &mariadb_args.build_options(),
+                    )?
+                } else {
+                    install::install(&filepath, &args.bin_path, &args.root)?
+                };
+                println!("Installed: {} into {:?}", manifest.version, manifest.prefix);
+                if !mariadb_args.no_init {
+                    init::init(
+                        &mariadb_args.instance(&args, &manifest.version)?,
+                        mariadb_args.root_authentication,
+                    )?;
+                }
+            }
+        }
+        Commands::Ls {} if mariadb_args.instances => {
+            let named_instances = instance::read_named_instances(&args.root)?;
+            if named_instances.is_empty() {
+                eprintln!("No named instances; `--instance <NAME>` creates one")
+            }
+            for named in named_instances.values() {
+                let instance = named.apply(mariadb_args.default_instance(&args, &named.version));
+                let status = match server::running_pid(&instance) {
+                    Some(pid) => format!("running (pid {})", pid),
+                    // The system tables are what initialisation creates
+                    None if !instance.data_path.join("mysql").is_dir() => {
+                        String::from("not initialised")
+                    }
+                    None => String::from("stopped"),
+                };
+                println!(
+                    "{}\t{}\t{}:{}\t{}\t{}\t{}",
+                    named.name,
+                    named.version,
+                    instance.hostname,
+                    instance.port,
+                    instance.socket().display(),
+                    status,
+                    instance.data_path.display()
+                )
+            }
+        }
+        Commands::Ls {} => {
+            // EOL dates from the cached release list; `ls` does not go to the network
+            let (snapshot, _) = mariadb_vm::offline_major_releases(&args.vm_root)?;
+            let today = chrono::Utc::now().date_naive();
+            let eol_policy = mariadb_args.eol_policy();
+            let bin_path = std::path::Path::new(args.bin_path.as_os_str());
+            let installed_versions = installed::list_installed(&args.root)?;
+            // The prefix `--bin-path` points at, or else whatever `--app-version` selects
+            let active_prefix = installed_versions
+                .iter()
+                .find(|installed| installed.prefix == bin_path)
+                .or_else(|| {
+                    installed::selected(
+                        &installed_versions,
+                        &args.app_version,
+                        &snapshot.major_releases,
+                    )
+                })
+                .map(|installed| installed.prefix.clone());
+            // Past its end of life with `--fail-on-eol`, but only once the table is out
+            let mut eol_error = None;
+            for installed in &installed_versions {
+                let active = active_prefix.as_ref() == Some(&installed.prefix);
+                let data_dir = if active {
+                    Some(std::path::PathBuf::from(&args.data_path)).filter(|dir| dir.is_dir())
+                } else {
+                    installed.sibling_data_dir()
+                };
+                let status = match installed::running_pids(&installed.prefix) {
+                    Some(pids) if pids.is_empty() => String::from("stopped"),
+                    Some(pids) => format!(
+                        "running (pid {})",
+                        pids.iter()
+                            .map(u32::to_string)
+                            .collect::<Vec<String>>()
+                            .join(", ")
+                    ),
+                    None => String::from("?"),
+                };
+                let release = eol::release_of(&installed.version, &snapshot.major_releases);
+                let eol = if active {
+                    match eol_policy.check(&installed.version, &snapshot.major_releases, today) {
+                        Ok(eol) => eol,
+                        Err(e) => {
+                            eol_error = Some(e);
+                            eol_policy.status(release, today)
+                        }
+                    }
+                } else {
+                    eol_policy.status(release, today)
+                };
+                println!(
+                    "{} {}\t{}\t{}\t{}\t{}\t{}\t{}",
+                    if active { "*" } else { " " },
+                    installed.version,
+                    match installed.installed_at {
+                        Some(installed_at) => installed_at.format("%Y-%m-%d").to_string(),
+                        None => String::from("-"),
+                    },
+                    installed.source,
+                    status,
+                    match data_dir {
+                        Some(data_dir) => format!(
+                            "data {:.1} MiB",
+                            installed::dir_size(&data_dir) as f64 / (1024.0 * 1024.0)
+                        ),
+                        None => String::from("no data"),
+                    },
+                    eol,
+                    installed.prefix.display()
+                )
+            }
+            if let Some(e) = eol_error {
+                return Err(e.into());
             }
         }
-        Commands::Ls {} => command::default_ls_command(&args)?,
         Commands::LsRemote {} => {
-            for release in releases {
-                println!("{}", release.release_id)
+            let format: release_list::ListFormat = match &mariadb_args.format {
+                Some(format) => format.parse()?,
+                None => release_list::ListFormat::default(),
+            };
+            if let Some((fetched_at, origin)) = &offline_releases_origin {
+                eprintln!(
+                    "Release list from {}, fetched {} days ago ({})",
+                    origin,
+                    (chrono::Utc::now() - *fetched_at).num_days(),
+                    fetched_at.format("%Y-%m-%d")
+                );
+            }
+            let today = chrono::Utc::now().date_naive();
+            let filter = mariadb_args.release_filter();
+            // Without one, binary availability is unknown rather than an error
+            let selector = mariadb_args.artifact_selector().ok();
+            let mut rows: Vec<release_list::RemoteRelease> = Vec::new();
+            for release in releases
+                .iter()
+                .filter(|release| filter.matches(release, today))
+            {
+                if !mariadb_args.point_releases {
+                    rows.push(release_list::RemoteRelease::series(release, today));
+                    continue;
+                }
+                match mariadb_vm::point_releases(
+                    &release.release_id,
+                    selector.as_ref(),
+                    &api_cache,
+                    &endpoints,
+                )
+                .await
+                {
+                    Ok(point_releases) => rows.extend(point_releases.iter().map(|point_release| {
+                        release_list::RemoteRelease::point_release(release, point_release, today)
+                    })),
+                    Err(e) => {
+                        eprintln!(
+                            "Could not list point releases of {}: {}",
+                            release.release_id, e
+                        );
+                        rows.push(release_list::RemoteRelease::series(release, today))
+                    }
+                }
+            }
+            print!("{}", release_list::render(&rows, format)?)
+        }
+        Commands::Uri {} => {
+            let format: uri::UriFormat = match &mariadb_args.format {
+                Some(format) => format.parse()?,
+                None => uri::UriFormat::default(),
+            };
+            let instance = mariadb_args.instance(&args, &installed_version(&args)?)?;
+            let credentials = if mariadb_args.with_credentials {
+                Some(uri::Credentials::of(&instance)?)
+            } else {
+                None
+            };
+            println!("{}", uri::render(&instance, format, credentials.as_ref()))
+        }
+        Commands::Service(ServiceCommands::Start { version }) => {
+            let instance = mariadb_args.service_instance(&args, version.as_deref())?;
+            mariadb_args.eol_policy().check(
+                &instance.version,
+                &releases,
+                chrono::Utc::now().date_naive(),
+            )?;
+            server::start(
+                &instance,
+                std::time::Duration::from_secs(mariadb_args.service_timeout),
+            )?;
+        }
+        Commands::Service(ServiceCommands::Stop { version }) => {
+            server::stop(
+                &mariadb_args.service_instance(&args, version.as_deref())?,
+                std::time::Duration::from_secs(mariadb_args.service_timeout),
+            )?;
+        }
+        Commands::Service(ServiceCommands::Reload { version }) => {
+            server::reload(&mariadb_args.service_instance(&args, version.as_deref())?)?;
+        }
+        Commands::Service(ServiceCommands::Install(InstallCommands::OpenRc {
+            group,
+            config_install_path,
+            service_install_path,
+            user,
+        })) => {
+            let instance = mariadb_args.instance(&args, &installed_version(&args)?)?;
+            let options = openrc::ScriptOptions {
+                user: user.clone(),
+                group: group.clone(),
+                root_authentication: mariadb_args.root_authentication,
+            };
+            let config_install_path = std::path::Path::new(config_install_path);
+            let service_install_path = std::path::Path::new(service_install_path);
+            // `openrc-run` only sources the `conf.d` file named like the script
+            if config_install_path.file_name() != service_install_path.file_name() {
+                eprintln!(
+                    "Warning: {:?} is not named like {:?}, so OpenRC will not read it",
+                    config_install_path, service_install_path
+                );
+            }
+            let files = [
+                (
+                    config_install_path,
+                    openrc::render_conf_d(&instance, &options),
+                    false,
+                ),
+                (service_install_path, openrc::render_init_script(), true),
+            ];
+            if mariadb_args.dry_run {
+                for (path, contents, _) in &files {
+                    println!("# {}", path.display());
+                    println!("{}", contents);
+                }
+            } else {
+                // The script runs the server with it
+                my_cnf::write(&instance)?;
+                for (path, contents, executable) in &files {
+                    if openrc::write(path, contents, *executable)? {
+                        println!("Wrote {:?}", path);
+                    } else {
+                        println!("{:?} is up to date", path);
+                    }
+                }
+                println!(
+                    "Enable it with: rc-update add {0} default && rc-service {0} start",
+                    service_install_path
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                );
+            }
+        }
+        Commands::Service(ServiceCommands::Install(InstallCommands::Systemd {
+            group,
+            service_install_path,
+            user,
+        })) => {
+            let instance = mariadb_args.instance(&args, &installed_version(&args)?)?;
+            let options = systemd::UnitOptions {
+                user: user.clone(),
+                group: group.clone(),
+                user_unit: mariadb_args.user_unit,
+                template_version: mariadb_args.template.then(|| args.app_version.clone()),
+            };
+            let unit = systemd::render(&instance, &options)?;
+            let unit_path =
+                systemd::unit_path(std::path::Path::new(service_install_path), &options)?;
+            if mariadb_args.dry_run {
+                println!("# {}", unit_path.display());
+                print!("{}", unit);
+            } else {
+                // The unit runs the server with it
+                my_cnf::write(&instance)?;
+                if systemd::write(&unit_path, &unit)? {
+                    println!("Wrote {:?}", unit_path);
+                } else {
+                    println!("{:?} is up to date", unit_path);
+                }
+                let systemctl = if options.user_unit {
+                    "systemctl --user"
+                } else {
+                    "systemctl"
+                };
+                let unit_name = match &options.template_version {
+                    Some(version) => unit_path
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .replace("@.service", &format!("@{}.service", version)),
+                    None => unit_path
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .to_string(),
+                };
+                println!(
+                    "Enable it with: {0} daemon-reload && {0} enable --now {1}",
+                    systemctl, unit_name
+                );
             }
         }
         _ => command::default_command(&args)?,
     }
     config::maybe_config_file_write(&args)?;
+    if args.config_write {
+        mariadb_args.config().write(&args.vms_config)?;
+    }
     Ok(())
 }
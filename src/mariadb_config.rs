@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+
+use crate::errors::MariaDbVmError;
+
+/// Key of the MariaDB-specific section in the vms-config JSON file.
+/// The rest of that file belongs to `version_manager_rs`.
+const CONFIG_KEY: &str = "mariadb";
+
+/// MariaDB-specific settings persisted in the vms-config file under `"mariadb"`
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct MariaDbConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_base: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archive_base: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mirrors_url: Option<String>,
+
+    /// Default for `download --mirror`, set with `mirrors --pin`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mirror_id: Option<String>,
+
+    /// Option files the managed `my.cnf` includes, set with `--my-cnf-include`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub my_cnf_include: Vec<std::path::PathBuf>,
+}
+
+impl MariaDbConfig {
+    /// Read the `"mariadb"` section of `vms_config`; empty when the file or section is absent
+    pub fn read(vms_config: &std::ffi::OsString) -> Result<Self, MariaDbVmError> {
+        let contents = match std::fs::read(vms_config) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(MariaDbVmError::from(e)),
+        };
+        let mut document: serde_json::Value = serde_json::from_slice(&contents)?;
+        match document.get_mut(CONFIG_KEY) {
+            Some(section) => Ok(serde_json::from_value(section.take())?),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Merge into the `"mariadb"` section of `vms_config`, leaving the rest of the file as is
+    pub fn write(&self, vms_config: &std::ffi::OsString) -> Result<(), MariaDbVmError> {
+        let mut document: serde_json::Value = match std::fs::read(vms_config) {
+            Ok(contents) => serde_json::from_slice(&contents)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                serde_json::Value::Object(serde_json::Map::new())
+            }
+            Err(e) => return Err(MariaDbVmError::from(e)),
+        };
+        if let Some(object) = document.as_object_mut() {
+            object.insert(String::from(CONFIG_KEY), serde_json::to_value(self)?);
+        }
+        if let Some(parent) = std::path::Path::new(vms_config).parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent)?;
+            }
+        }
+        std::fs::write(vms_config, serde_json::to_vec_pretty(&document)?)?;
+        Ok(())
+    }
+}
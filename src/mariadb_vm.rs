@@ -1,15 +1,17 @@
 extern crate reqwest;
 
-use std::cmp::Ordering;
-
-use rand::Rng;
 use serde::{Deserialize, Serialize};
-use sha2::Digest;
 
+use crate::api_cache::ApiCache;
+use crate::artifact::{ArtifactSelector, PackageType};
+use crate::checksum::ChecksumHasher;
 use crate::errors::MariaDbVmError;
+use crate::mirror::{self, MirrorOptions};
+use crate::signature::{self, SignatureOptions, SignatureSource};
+use crate::version::{MariaDbVersion, VersionReq, VersionSelector};
 
-#[derive(Clone, Deserialize, Serialize)]
-struct Checksum {
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Checksum {
     pub md5sum: Option<String>,
     pub sha1sum: Option<String>,
     pub sha256sum: Option<String>,
@@ -32,7 +34,7 @@ struct ListOfFileChecksumsRoot {
 
 /* Begin https://mariadb.org/downloads-rest-api/#list-of-point-releases-and-files */
 #[derive(Clone, Serialize, Deserialize)]
-struct Files {
+pub struct Files {
     pub file_id: i64,
     pub file_name: String,
     pub package_type: Option<String>,
@@ -47,7 +49,7 @@ struct Files {
 
 #[derive(Clone, Serialize, Deserialize)]
 struct Release {
-    pub release_id: String,
+    pub release_id: MariaDbVersion,
     pub release_name: String,
     pub date_of_release: String,
     pub release_notes_url: String,
@@ -64,235 +66,556 @@ struct ListOfPointReleasesAndFilesRoot {
 /* Start https://mariadb.org/downloads-rest-api/#list-available-mirrors */
 
 #[derive(Serialize, Deserialize)]
-struct Mirror {
+pub struct Mirror {
     pub mirror_id: String,
     pub mirror_name: String,
 }
 
 #[derive(Serialize, Deserialize)]
-struct ListAvailableMirrorsRoot {
+pub struct ListAvailableMirrorsRoot {
     pub mirror_list: std::collections::HashMap<String, Vec<Mirror>>,
 }
 
 /* End https://mariadb.org/downloads-rest-api/#list-available-mirrors */
 
-const API_BASE: &'static str = "https://downloads.mariadb.org/rest-api/mariadb";
+const API_BASE: &str = "https://downloads.mariadb.org/rest-api/mariadb";
+const ARCHIVE_BASE: &str = "https://archive.mariadb.org";
+const MIRRORS_URL: &str = "https://downloads.mariadb.org/rest-api/mirrors";
+
+/// Where the MariaDB REST API, the archive of releases, and the mirror list are served from.
+/// Point these at an internal proxy or a local stand-in to avoid the public hosts.
+#[derive(Clone, Debug)]
+pub struct Endpoints {
+    pub api_base: String,
+    pub archive_base: String,
+    pub mirrors_url: String,
+}
+
+impl Endpoints {
+    pub fn new(
+        api_base: Option<String>,
+        archive_base: Option<String>,
+        mirrors_url: Option<String>,
+    ) -> Self {
+        Endpoints {
+            api_base: api_base
+                .unwrap_or_else(|| String::from(API_BASE))
+                .trim_end_matches('/')
+                .to_string(),
+            archive_base: archive_base
+                .unwrap_or_else(|| String::from(ARCHIVE_BASE))
+                .trim_end_matches('/')
+                .to_string(),
+            mirrors_url: mirrors_url.unwrap_or_else(|| String::from(MIRRORS_URL)),
+        }
+    }
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Endpoints::new(None, None, None)
+    }
+}
+
+/// Everything needed to fetch and verify an archive, resolved ahead of time so it can be
+/// reviewed (and approved) before being executed with [`download_from_plan`]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DownloadPlan {
+    /// Exact major.minor.patch version
+    pub version: String,
+    pub filename: String,
+    pub url: String,
+    pub mirror_id: Option<String>,
+    pub file_id: Option<i64>,
+    pub checksum: Checksum,
+    /// ASCII-armored detached signature, when the REST API inlines it
+    pub signature: Option<String>,
+    pub signature_url: Option<String>,
+    /// Tried in order when downloading from `url` fails or does not match `checksum`
+    #[serde(default)]
+    pub fallback_mirrors: Vec<MirrorUrl>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MirrorUrl {
+    /// `None` for the REST API's own download URL, which leaves the mirror to the archive
+    #[serde(default)]
+    pub mirror_id: Option<String>,
+    pub url: String,
+}
+
+impl DownloadPlan {
+    /// Whether this is the source tarball rather than a binary
+    pub fn is_source(&self) -> bool {
+        self.filename == format!("mariadb-{}.tar.gz", self.version)
+    }
+
+    fn signature_source(&self) -> Result<Option<SignatureSource>, MariaDbVmError> {
+        Ok(match (&self.signature, &self.signature_url) {
+            (Some(signature), _) => Some(SignatureSource::Inline(signature.clone())),
+            (None, Some(url)) => Some(SignatureSource::Url(reqwest::Url::parse(url)?)),
+            (None, None) => None,
+        })
+    }
+}
+
+pub fn http_client() -> Result<reqwest::Client, MariaDbVmError> {
+    Ok(reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::limited(1))
+        .build()?)
+}
 
-/// This download function makes up-to 3 requests: get exact version, checksum, mirror, archive
+/// Resolve `version` to a [`DownloadPlan`] with [`download_plan`] (the point release, the
+/// file `selector` picks, its checksum and signature, and the mirrors to try), then execute
+/// it with [`download_from_plan`], which falls over to the next mirror, and then the archive,
+/// when one fails
+#[allow(clippy::too_many_arguments)]
 pub async fn download(
     version: &str,
     target_dir: &std::ffi::OsString,
     force: bool,
-    mirror: &Option<String>,
+    selector: &ArtifactSelector,
+    mirror_options: &MirrorOptions,
+    signature_options: &SignatureOptions,
+    api_cache: &ApiCache,
+    endpoints: &Endpoints,
 ) -> Result<Option<std::ffi::OsString>, MariaDbVmError> {
-    let client = reqwest::Client::builder()
-        .redirect(reqwest::redirect::Policy::limited(1))
-        .build()?;
-
-    let mut checksum: Option<Checksum> = None;
-    let mut file_id: Option<i64> = None;
-
-    let major_minor_patch: std::borrow::Cow<str> =
-        if version.chars().filter(|c| *c == '.').count() > 1 {
-            std::borrow::Cow::from(version)
-        } else {
-            // https://mariadb.org/downloads-rest-api/#list-of-point-releases-and-files
-            let list_of_point_releases_and_files_url = reqwest::Url::parse(&format!(
-                "{API_BASE}/{version}/",
-                API_BASE = API_BASE,
-                version = version
-            ))?;
-            println!("GET {}", list_of_point_releases_and_files_url);
-            let list_of_point_releases_and_files_root: ListOfPointReleasesAndFilesRoot = client
-                .get(list_of_point_releases_and_files_url)
-                .send()
-                .await?
-                .json()
-                .await?;
-            // Last element is the newest version
-            let release = list_of_point_releases_and_files_root
-                .releases
-                .values()
-                .rev()
-                .cloned()
-                .next()
-                .unwrap();
-
-            let os = match std::env::consts::OS {
-                "linux" => "Linux",
-                "windows" => "Windows",
-                // "freebsd" => "FreeBSD", // actually I don't think this ever appears
-                _ => "Source",
-            };
+    let plan = download_plan(version, selector, mirror_options, api_cache, endpoints).await?;
+    download_from_plan(&plan, target_dir, force, signature_options).await
+}
 
-            if let Some(file) = release
-                .files
-                .iter()
-                .find(|file| file.os == Some(String::from(os)))
-            {
-                file_id = Some(file.file_id);
-                checksum = Some(file.checksum.clone());
-            };
-            std::borrow::Cow::from(release.release_id)
-        };
-
-    let (download_url, filename) = {
-        let (base_url, filename) = match std::env::consts::OS {
-            "linux" => (
-                format!(
-                    "{API_BASE}/{major_minor_patch}/",
-                    API_BASE = API_BASE,
-                    major_minor_patch = major_minor_patch
-                ),
-                format!(
-                    "mariadb-{major_minor_patch}-linux-systemd-{arch}.tar.gz",
-                    major_minor_patch = major_minor_patch,
-                    arch = std::env::consts::ARCH
-                ),
-            ),
-            "freebsd" => (
-                format!(
-                    "https://archive.mariadb.org/mariadb-{major_minor_patch}/bintar-freebsd130-{arch}/",
-                    major_minor_patch = major_minor_patch,
-                    arch = std::env::consts::ARCH
-                ),
-                format!(
-                    "mariadb-{major_minor_patch}-freebsd13.0-{arch}.tar.gz",
-                    major_minor_patch = major_minor_patch,
-                    arch = std::env::consts::ARCH
-                ),
-            ),
-            "windows" => (
-                format!(
-                    "{API_BASE}/{major_minor_patch}/",
-                    API_BASE = API_BASE,
-                    major_minor_patch = major_minor_patch
-                ),
-                format!(
-                    "mariadb-{major_minor_patch}-win{arch}.zip",
-                    major_minor_patch = major_minor_patch,
-                    arch = match std::env::consts::ARCH {
-                        "x86" => "x32",
-                        "x86_64" => "x64",
-                        a => a,
-                    }
-                ),
-            ),
-            _ => unimplemented!("Build from source"),
-        };
+/// https://mariadb.org/downloads-rest-api/#list-of-point-releases-and-files
+async fn list_point_releases(
+    client: &reqwest::Client,
+    api_cache: &ApiCache,
+    endpoints: &Endpoints,
+    series: &MariaDbVersion,
+) -> Result<ListOfPointReleasesAndFilesRoot, MariaDbVmError> {
+    let list_of_point_releases_and_files_url = reqwest::Url::parse(&format!(
+        "{API_BASE}/{series}/",
+        API_BASE = endpoints.api_base,
+        series = series
+    ))?;
+    api_cache
+        .get_json(client, list_of_point_releases_and_files_url)
+        .await
+}
 
-        (
-            reqwest::Url::parse(&format!("{}{}", base_url, filename))?,
-            filename,
-        )
+/// https://mariadb.org/downloads-rest-api/#list-available-mirrors
+pub async fn list_mirrors(
+    client: &reqwest::Client,
+    api_cache: &ApiCache,
+    endpoints: &Endpoints,
+) -> Result<ListAvailableMirrorsRoot, MariaDbVmError> {
+    let mirrors_url = reqwest::Url::parse(&endpoints.mirrors_url)?;
+    api_cache.get_json(client, mirrors_url).await
+}
+
+/// Download URL of `file_id`, served by `mirror_id`
+pub fn mirror_download_url(
+    endpoints: &Endpoints,
+    major_minor_patch: &str,
+    file_id: i64,
+    mirror_id: &str,
+) -> Result<reqwest::Url, MariaDbVmError> {
+    Ok(reqwest::Url::parse_with_params(
+        &format!(
+            "{API_BASE}/{major_minor_patch}/{file_id}",
+            API_BASE = endpoints.api_base,
+            major_minor_patch = major_minor_patch,
+            file_id = file_id
+        ),
+        &[("mirror", mirror_id)],
+    )?)
+}
+
+/// Numerically newest point release satisfying `predicate`; the map's keys sort as strings,
+/// so 10.6.9 would otherwise come after 10.6.10
+fn newest_point_release(
+    list_of_point_releases_and_files_root: &ListOfPointReleasesAndFilesRoot,
+    predicate: impl Fn(&MariaDbVersion) -> bool,
+) -> Option<Release> {
+    list_of_point_releases_and_files_root
+        .releases
+        .values()
+        .filter(|release| predicate(&release.release_id))
+        .max_by(|rel0, rel1| rel0.release_id.cmp(&rel1.release_id))
+        .cloned()
+}
+
+/// A point release, as listed by the REST API
+#[derive(Clone, Debug, Serialize)]
+pub struct PointRelease {
+    pub release_id: MariaDbVersion,
+    pub release_name: String,
+    pub date_of_release: String,
+    /// Whether the selector matches one of its files; `None` when the REST API does not
+    /// list this platform at all, or there is no selector
+    pub has_binary: Option<bool>,
+}
+
+/// Every point release of `series`, oldest first
+pub async fn point_releases(
+    series: &MariaDbVersion,
+    selector: Option<&ArtifactSelector>,
+    api_cache: &ApiCache,
+    endpoints: &Endpoints,
+) -> Result<Vec<PointRelease>, MariaDbVmError> {
+    let client = http_client()?;
+    let mut point_releases: Vec<PointRelease> =
+        list_point_releases(&client, api_cache, endpoints, &series.series())
+            .await?
+            .releases
+            .into_values()
+            .map(|release| PointRelease {
+                has_binary: selector
+                    .filter(|selector| selector.os_is_listed(&release.files))
+                    .map(|selector| selector.select(&release.files).is_some()),
+                release_id: release.release_id,
+                release_name: release.release_name,
+                date_of_release: release.date_of_release,
+            })
+            .collect();
+    point_releases.sort_by(|rel0, rel1| rel0.release_id.cmp(&rel1.release_id));
+    Ok(point_releases)
+}
+
+/// Resolve the exact version, filename, mirror, and checksum of a download without
+/// downloading the archive itself
+pub async fn download_plan(
+    version: &str,
+    selector: &ArtifactSelector,
+    mirror_options: &MirrorOptions,
+    api_cache: &ApiCache,
+    endpoints: &Endpoints,
+) -> Result<DownloadPlan, MariaDbVmError> {
+    let client = http_client()?;
+
+    let requested: MariaDbVersion = version.parse()?;
+    let list_of_point_releases_and_files_root =
+        list_point_releases(&client, api_cache, endpoints, &requested.series()).await?;
+    let release = match requested.patch {
+        Some(_) => list_of_point_releases_and_files_root
+            .releases
+            .values()
+            .find(|release| release.release_id == requested)
+            .cloned(),
+        None => newest_point_release(&list_of_point_releases_and_files_root, |_| true),
+    };
+
+    let (major_minor_patch, release_files): (String, Vec<Files>) = match release {
+        Some(release) => (release.release_id.to_string(), release.files),
+        None if requested.patch.is_some() => (requested.to_string(), Vec::with_capacity(0)),
+        None => {
+            return Err(MariaDbVmError::NoMatchingRelease {
+                selector: version.to_string(),
+            })
+        }
+    };
+
+    let listed_file: Option<Files> = selector.select(&release_files).cloned();
+    let (download_url, filename) = match &listed_file {
+        Some(file) => (
+            reqwest::Url::parse(&format!(
+                "{API_BASE}/{major_minor_patch}/{filename}",
+                API_BASE = endpoints.api_base,
+                major_minor_patch = major_minor_patch,
+                filename = file.file_name
+            ))?,
+            file.file_name.clone(),
+        ),
+        // Not listed for this OS at all (e.g., FreeBSD), so fall back to the conventional
+        // filenames and URLs
+        None if !selector.os_is_listed(&release_files) => {
+            match selector.conventional_download(endpoints, &major_minor_patch) {
+                Some((base_url, filename)) => (
+                    reqwest::Url::parse(&format!("{}{}", base_url, filename))?,
+                    filename,
+                ),
+                None => {
+                    return Err(MariaDbVmError::NoMatchingArtifact {
+                        selector: selector.to_string(),
+                        available: Vec::with_capacity(0),
+                    })
+                }
+            }
+        }
+        None => {
+            return Err(MariaDbVmError::NoMatchingArtifact {
+                selector: selector.to_string(),
+                available: release_files
+                    .iter()
+                    .map(|file| file.file_name.clone())
+                    .collect(),
+            })
+        }
     };
+    let file_id: Option<i64> = listed_file.as_ref().map(|file| file.file_id);
+    let mut checksum: Option<Checksum> = listed_file.as_ref().map(|file| file.checksum.clone());
 
     if checksum.is_none() {
         let checksum_url = reqwest::Url::parse(&format!(
             "{API_BASE}/{major_minor_patch}/{filename}/checksum",
-            API_BASE = API_BASE,
+            API_BASE = endpoints.api_base,
             major_minor_patch = major_minor_patch,
             filename = filename
         ))?;
-        println!("GET {}", checksum_url);
-        let checksum_response = client.get(checksum_url).send().await?;
-        let checksum_root: ListOfFileChecksumsRoot = checksum_response.json().await?;
+        let checksum_root: ListOfFileChecksumsRoot =
+            api_cache.get_json(&client, checksum_url).await?;
         checksum = Some(checksum_root.response.checksum);
     }
 
-    let target_dir = std::path::Path::new(target_dir.as_os_str())
-        .join("downloads")
-        .join("mariadb");
-    let target_file = target_dir.join(filename);
-
-    let checksum_func = |sh256: &String| -> Result<bool, MariaDbVmError> {
-        let mut hasher = sha2::Sha256::new();
-        let mut file = std::fs::File::open(&target_file)?;
-        let bytes_written = std::io::copy(&mut file, &mut hasher)?;
-        assert!(bytes_written > 0);
-        let hash_bytes = hasher.finalize();
-        if sh256.as_bytes().cmp(&hash_bytes[..]) != Ordering::Equal {
-            eprintln!("{:x?}\n\t!=\n{:x?}", sh256.as_bytes(), &hash_bytes[..]);
-            Err(MariaDbVmError::from(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                "SHA256 verification failed",
-            )))
-        } else {
-            Ok(true)
+    let check_sum = checksum.unwrap();
+
+    let (signature, signature_url) = match &listed_file {
+        Some(file) => match &file.signature {
+            Some(signature) if signature.contains("BEGIN PGP SIGNATURE") => {
+                (Some(signature.clone()), None)
+            }
+            _ => (None, Some(file.signature_url.clone())),
+        },
+        None => (None, Some(format!("{}.asc", download_url))),
+    };
+
+    let mut mirror_urls: Vec<MirrorUrl> = match file_id {
+        // Only the file_id URL lets the mirror be picked
+        Some(file_id) => {
+            let list_available_mirrors_root: ListAvailableMirrorsRoot =
+                match list_mirrors(&client, api_cache, endpoints).await {
+                    Ok(root) => root,
+                    Err(e) if mirror_options.mirror.is_some() => {
+                        eprintln!("Could not list mirrors, so no fallback mirrors: {}", e);
+                        ListAvailableMirrorsRoot {
+                            mirror_list: std::collections::HashMap::new(),
+                        }
+                    }
+                    Err(e) => return Err(e),
+                };
+
+            let mirror_url = |mirror_id: &str| {
+                mirror_download_url(endpoints, &major_minor_patch, file_id, mirror_id)
+            };
+            mirror::rank_mirrors(
+                &client,
+                &list_available_mirrors_root,
+                mirror_options,
+                &mirror_url,
+            )
+            .await?
+            .into_iter()
+            .map(|mirror_id| {
+                Ok(MirrorUrl {
+                    url: mirror_url(&mirror_id)?.to_string(),
+                    mirror_id: Some(mirror_id),
+                })
+            })
+            .collect::<Result<_, MariaDbVmError>>()?
         }
+        None => Vec::with_capacity(0),
     };
-    let check_sum = checksum.unwrap();
 
-    let checksum_sha256 = check_sum.sha256sum.unwrap();
-    // could check other checksums here if sha256 isn't defined
+    // When every mirror picked fails, the archive may still serve the file
+    mirror_urls.push(MirrorUrl {
+        mirror_id: None,
+        url: download_url.to_string(),
+    });
+    let first = mirror_urls.remove(0);
+    Ok(DownloadPlan {
+        version: major_minor_patch,
+        filename,
+        url: first.url,
+        mirror_id: first.mirror_id,
+        file_id,
+        checksum: check_sum,
+        signature,
+        signature_url,
+        fallback_mirrors: mirror_urls,
+    })
+}
+
+/// [`download_plan`], falling back to the source tarball when no binary matches `selector`
+pub async fn download_plan_or_source(
+    version: &str,
+    selector: &ArtifactSelector,
+    mirror_options: &MirrorOptions,
+    api_cache: &ApiCache,
+    endpoints: &Endpoints,
+) -> Result<DownloadPlan, MariaDbVmError> {
+    match download_plan(version, selector, mirror_options, api_cache, endpoints).await {
+        Err(e @ MariaDbVmError::NoMatchingArtifact { .. })
+            if selector.package_type != PackageType::Source =>
+        {
+            eprintln!("{}\nBuilding from source instead", e);
+            download_plan(
+                version,
+                &ArtifactSelector::source(),
+                mirror_options,
+                api_cache,
+                endpoints,
+            )
+            .await
+        }
+        result => result,
+    }
+}
+
+/// Fetch and verify the archive described by `plan`, without consulting the REST API
+pub async fn download_from_plan(
+    plan: &DownloadPlan,
+    target_dir: &std::ffi::OsString,
+    force: bool,
+    signature_options: &SignatureOptions,
+) -> Result<Option<std::ffi::OsString>, MariaDbVmError> {
+    let client = http_client()?;
+
+    let target_dir = std::path::Path::new(target_dir.as_os_str())
+        .join("downloads")
+        .join("mariadb");
+    let target_file = target_dir.join(&plan.filename);
+    let keys_dir = target_dir.join("keys");
 
     if !force && target_file.is_file() {
-        match checksum_func(&checksum_sha256) {
-            Ok(passed) => {
-                if passed {
-                    return Ok(Some(target_file.into_os_string()));
-                } else {
-                }
+        match plan.checksum.verify(&target_file) {
+            Ok(()) => {
+                signature::verify_archive(
+                    &client,
+                    &target_file,
+                    plan.signature_source()?,
+                    signature_options,
+                    &keys_dir,
+                )
+                .await?;
+                return Ok(Some(target_file.into_os_string()));
             }
-            Err(_) => {}
+            Err(e) => eprintln!("Re-downloading {:?}: {}", target_file, e),
         }
-        // Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "Checksum not found"));
     } else if !target_dir.is_dir() {
-        std::fs::create_dir_all(target_dir)?;
+        std::fs::create_dir_all(&target_dir)?;
     }
 
-    let mirror_id: String = if std::env::consts::OS == "freebsd" {
-        String::from("")
-    } else if let Some(id) = mirror {
-        String::from(id)
-    } else {
-        let mirrors_url = reqwest::Url::parse("https://downloads.mariadb.org/rest-api/mirrors")?;
-        println!("GET {}", mirrors_url);
-        let mirrors_response = client.get(mirrors_url).send().await?;
-        let list_available_mirrors_root: ListAvailableMirrorsRoot = mirrors_response.json().await?;
-
-        // Choose a random mirror. In reality, you may want to choose one closer to your IP? GeoIP?
-        let mut rng = rand::thread_rng();
-        let rand_i: usize = rng.gen_range(0..list_available_mirrors_root.mirror_list.len());
-        let mirrors = list_available_mirrors_root
-            .mirror_list
-            .values()
-            .skip(rand_i)
-            .next()
-            .unwrap();
-        let rand_j: usize = rng.gen_range(0..mirrors.len());
-        String::from(mirrors.get(rand_j).unwrap().mirror_id.as_str())
+    let candidates: Vec<(Option<&str>, &str)> =
+        std::iter::once((plan.mirror_id.as_deref(), plan.url.as_str()))
+            .chain(
+                plan.fallback_mirrors
+                    .iter()
+                    .map(|mirror| (mirror.mirror_id.as_deref(), mirror.url.as_str())),
+            )
+            .collect();
+    let mut failures: Vec<String> = Vec::new();
+    for (mirror_id, url) in &candidates {
+        let served_by = mirror_id.unwrap_or(url);
+        match download_file(
+            &client,
+            reqwest::Url::parse(url)?,
+            &target_file,
+            &plan.checksum,
+        )
+        .await
+        {
+            Ok(()) => {
+                eprintln!("Downloaded {} from mirror {}", plan.filename, served_by);
+                break;
+            }
+            Err(e @ (MariaDbVmError::Reqwest { .. } | MariaDbVmError::ChecksumMismatch { .. }))
+                if candidates.len() > 1 =>
+            {
+                eprintln!("Mirror {} failed: {}", served_by, e);
+                failures.push(format!("{}: {}", served_by, e));
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    if failures.len() == candidates.len() {
+        return Err(MariaDbVmError::AllMirrorsFailed { failures });
+    }
+    signature::verify_archive(
+        &client,
+        &target_file,
+        plan.signature_source()?,
+        signature_options,
+        &keys_dir,
+    )
+    .await?;
+    Ok(Some(std::ffi::OsString::from(target_file)))
+}
+
+/// Stream `url` into `<target_file>.part`, hashing as it arrives, and rename it to
+/// `target_file` only once the checksum matches. An existing `.part` file is resumed with
+/// an HTTP Range request; a server that ignores the range restarts the download.
+pub async fn download_file(
+    client: &reqwest::Client,
+    url: reqwest::Url,
+    target_file: &std::path::Path,
+    checksum: &Checksum,
+) -> Result<(), MariaDbVmError> {
+    let (algorithm, expected) = checksum.strongest()?;
+    let part_file = {
+        let mut part_name = target_file.as_os_str().to_os_string();
+        part_name.push(".part");
+        std::path::PathBuf::from(part_name)
+    };
+
+    let mut hasher = ChecksumHasher::new(algorithm);
+    let already_downloaded: u64 = match std::fs::metadata(&part_file) {
+        Ok(metadata) if metadata.is_file() => {
+            std::io::copy(&mut std::fs::File::open(&part_file)?, &mut hasher)?
+        }
+        _ => 0,
     };
 
-    let response = if std::env::consts::OS == "freebsd" || file_id.is_none() {
-        println!("GET {}", download_url);
-        client.get(download_url).send().await?
+    let mut request = client.get(url.clone());
+    if already_downloaded > 0 {
+        request = request.header(
+            reqwest::header::RANGE,
+            format!("bytes={}-", already_downloaded),
+        );
+        eprintln!("GET {} (resuming from byte {})", url, already_downloaded);
     } else {
-        // This alternative URL allows a provided mirror
-        let dl_url = reqwest::Url::parse_with_params(
-            &format!(
-                "{API_BASE}/{major_minor_patch}/{file_id}",
-                API_BASE = API_BASE,
-                major_minor_patch = major_minor_patch,
-                file_id = file_id.unwrap()
-            ),
-            &[("mirror", mirror_id)],
-        )?;
-        println!("GET {}", dl_url);
-        client.get(dl_url).send().await?
+        eprintln!("GET {}", url);
+    }
+    let mut response = request.send().await?;
+
+    let mut file = if already_downloaded > 0
+        && response.status() == reqwest::StatusCode::RANGE_NOT_SATISFIABLE
+    {
+        // Nothing left to fetch: the `.part` file already holds the whole body
+        None
+    } else if already_downloaded > 0 && response.status() == reqwest::StatusCode::PARTIAL_CONTENT {
+        Some(std::fs::OpenOptions::new().append(true).open(&part_file)?)
+    } else {
+        response = response.error_for_status()?;
+        hasher = ChecksumHasher::new(algorithm);
+        Some(std::fs::File::create(&part_file)?)
     };
-    std::fs::write(&target_file, response.bytes().await?)?;
-    checksum_func(&checksum_sha256)?;
-    Ok(Some(std::ffi::OsString::from(target_file)))
+
+    if let Some(file) = file.as_mut() {
+        let mut writer = std::io::BufWriter::new(file);
+        while let Some(chunk) = response.chunk().await? {
+            hasher.update(&chunk);
+            std::io::Write::write_all(&mut writer, &chunk)?;
+        }
+        std::io::Write::flush(&mut writer)?;
+    }
+
+    match Checksum::verify_digest(algorithm, &expected, &hasher.finalize()) {
+        Ok(()) => {
+            std::fs::rename(&part_file, target_file)?;
+            Ok(())
+        }
+        Err(e) => {
+            // A corrupt partial file must not be resumed next time
+            std::fs::remove_file(&part_file)?;
+            Err(e)
+        }
+    }
 }
 
 mod date_format_month_year_day {
     use chrono::NaiveDate;
     use serde::{Deserialize, Deserializer, Serializer};
 
-    const FORMAT: &'static str = "%Y-%m-%d";
+    const FORMAT: &str = "%Y-%m-%d";
 
-    #[allow(dead_code)]
     pub fn serialize<S>(date: &Option<NaiveDate>, s: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
@@ -307,9 +630,10 @@ mod date_format_month_year_day {
     where
         D: Deserializer<'de>,
     {
-        match Option::deserialize(deserializer)? {
+        // Owned, as readers (e.g., the cache file) cannot lend out borrowed strings
+        match Option::<String>::deserialize(deserializer)? {
             Some(s) => Ok(Some(
-                NaiveDate::parse_from_str(s, FORMAT).map_err(serde::de::Error::custom)?,
+                NaiveDate::parse_from_str(&s, FORMAT).map_err(serde::de::Error::custom)?,
             )),
             None => Ok(None),
         }
@@ -324,9 +648,9 @@ struct ListOfMajorAndMinorReleases {
     major_releases: Vec<MajorReleases>,
 }
 
-#[derive(Clone, serde::Deserialize, Debug)]
+#[derive(Clone, serde::Deserialize, serde::Serialize, Debug)]
 pub struct MajorReleases {
-    pub release_id: String,
+    pub release_id: MariaDbVersion,
     pub release_name: String,
     pub release_status: String,
     pub release_support_type: String,
@@ -336,6 +660,23 @@ pub struct MajorReleases {
     pub release_eol_date: Option<chrono::NaiveDate>,
 }
 
+impl MajorReleases {
+    /// Whether this series is past its end of life on `today`; `None` when no date is published
+    pub fn is_eol(&self, today: chrono::NaiveDate) -> Option<bool> {
+        self.release_eol_date.map(|eol_date| eol_date <= today)
+    }
+}
+
+/// The release series `version` belongs to, if it is in `releases`
+pub fn major_release<'a>(
+    version: &MariaDbVersion,
+    releases: &'a [MajorReleases],
+) -> Option<&'a MajorReleases> {
+    releases
+        .iter()
+        .find(|release| release.release_id == version.series())
+}
+
 /*
 # To support old versions for testing purposes which are not on dlm.mariadb.com
 case ${mariadb_server_version} in
@@ -353,140 +694,172 @@ case ${mariadb_server_version} in
 esac
 */
 
-const AOT_MARIADB_VERSIONS_LEN: usize = 10;
-
-fn parse_from_year_month_date(date_str: &str) -> chrono::ParseResult<chrono::NaiveDate> {
-    chrono::NaiveDate::parse_from_str(date_str, "%Y-%m-%d")
+/// Release list together with when it was fetched from the REST API, as cached under
+/// `vm_root` and as embedded at build time
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct MajorReleasesSnapshot {
+    pub fetched_at: chrono::DateTime<chrono::Utc>,
+    pub major_releases: Vec<MajorReleases>,
 }
 
 // From https://dlm.mariadb.com/rest/releases/mariadb_server/ 2024-04-06
-pub fn offline_major_releases() -> Vec<MajorReleases> {
-    let mut releases: Vec<MajorReleases> = Vec::with_capacity(AOT_MARIADB_VERSIONS_LEN);
-
-    releases[0] = MajorReleases {
-        release_id: String::from("10.4"),
-        release_name: String::from("MariaDB Server 10.4"),
-        release_status: String::from("Stable"),
-        release_support_type: String::from("Long Term Support"),
-        release_eol_date: Some(parse_from_year_month_date("2024-06-18").unwrap()),
-    };
-    releases[1] = MajorReleases {
-        release_id: String::from("10.5"),
-        release_name: String::from("MariaDB Server 10.5"),
-        release_status: String::from("Stable"),
-        release_support_type: String::from("Long Term Support"),
-        release_eol_date: Some(parse_from_year_month_date("2025-06-24").unwrap()),
-    };
-    releases[2] = MajorReleases {
-        release_id: String::from("10.6"),
-        release_name: String::from("MariaDB Server 10.6"),
-        release_status: String::from("Stable"),
-        release_support_type: String::from("Long Term Support"),
-        release_eol_date: Some(parse_from_year_month_date("2026-07-06").unwrap()),
-    };
-    releases[3] = MajorReleases {
-        release_id: String::from("10.11"),
-        release_name: String::from("MariaDB Server 10.11"),
-        release_status: String::from("Stable"),
-        release_support_type: String::from("Long Term Support"),
-        release_eol_date: Some(parse_from_year_month_date("2028-02-16").unwrap()),
-    };
-    releases[4] = MajorReleases {
-        release_id: String::from("11.0"),
-        release_name: String::from("MariaDB Server 11.0"),
-        release_status: String::from("Stable"),
-        release_support_type: String::from("Short Term Support"),
-        release_eol_date: Some(parse_from_year_month_date("2024-06-06").unwrap()),
-    };
-    releases[5] = MajorReleases {
-        release_id: String::from("11.1"),
-        release_name: String::from("MariaDB Server 11.1"),
-        release_status: String::from("Stable"),
-        release_support_type: String::from("Short Term Support"),
-        release_eol_date: Some(parse_from_year_month_date("2024-08-21").unwrap()),
-    };
-    releases[6] = MajorReleases {
-        release_id: String::from("11.2"),
-        release_name: String::from("MariaDB Server 11.2"),
-        release_status: String::from("Stable"),
-        release_support_type: String::from("Short Term Support"),
-        release_eol_date: Some(parse_from_year_month_date("2024-11-21").unwrap()),
-    };
-    releases[7] = MajorReleases {
-        release_id: String::from("11.3"),
-        release_name: String::from("MariaDB Server 11.3"),
-        release_status: String::from("Stable"),
-        release_support_type: String::from("Short Term Support"),
-        release_eol_date: None,
-    };
-    releases[8] = MajorReleases {
-        release_id: String::from("11.4"),
-        release_name: String::from("MariaDB Server 11.4"),
-        release_status: String::from("RC"),
-        release_support_type: String::from("Long Term Support"),
-        release_eol_date: None,
-    };
-    releases[9] = MajorReleases {
-        release_id: String::from("11.5"),
-        release_name: String::from("MariaDB Server 11.5"),
-        release_status: String::from("Alpha"),
-        release_support_type: String::from("Short Term Support"),
-        release_eol_date: None,
-    };
+const EMBEDDED_MAJOR_RELEASES_SNAPSHOT: &str = include_str!("../data/major_releases_snapshot.json");
 
-    releases
+fn major_releases_cache_file(vm_root: &std::ffi::OsString) -> std::path::PathBuf {
+    std::path::Path::new(vm_root.as_os_str())
+        .join("cache")
+        .join("mariadb")
+        .join("major_releases.json")
+}
+
+/// Last release list successfully fetched by [`versions_from_remote`], falling back to the
+/// snapshot embedded at build time. The path is that of the cache file, if it was used.
+pub fn offline_major_releases(
+    vm_root: &std::ffi::OsString,
+) -> Result<(MajorReleasesSnapshot, Option<std::path::PathBuf>), MariaDbVmError> {
+    let cache_file = major_releases_cache_file(vm_root);
+    if cache_file.is_file() {
+        match std::fs::File::open(&cache_file)
+            .map_err(MariaDbVmError::from)
+            .and_then(|file| {
+                serde_json::from_reader::<_, MajorReleasesSnapshot>(std::io::BufReader::new(file))
+                    .map_err(MariaDbVmError::from)
+            }) {
+            Ok(snapshot) => return Ok((snapshot, Some(cache_file))),
+            Err(e) => eprintln!("Ignoring unreadable {:?}: {}", cache_file, e),
+        }
+    }
+    Ok((
+        serde_json::from_str(EMBEDDED_MAJOR_RELEASES_SNAPSHOT)?,
+        None,
+    ))
 }
 
-pub async fn versions_from_remote() -> Result<Vec<MajorReleases>, Box<dyn std::error::Error>> {
-    let response = reqwest::get(format!("{API_BASE}/", API_BASE = API_BASE)).await?;
-    let mut list_of_major_and_minor_releases: ListOfMajorAndMinorReleases = response.json().await?;
+/// Fetch the release list, sorted oldest to newest, and cache it under `vm_root` for
+/// [`offline_major_releases`]
+pub async fn versions_from_remote(
+    vm_root: &std::ffi::OsString,
+    api_cache: &ApiCache,
+    endpoints: &Endpoints,
+) -> Result<Vec<MajorReleases>, Box<dyn std::error::Error>> {
+    let (mut list_of_major_and_minor_releases, fetched_at): (ListOfMajorAndMinorReleases, _) =
+        api_cache
+            .get_json_with_fetched_at(
+                &http_client()?,
+                reqwest::Url::parse(&format!("{API_BASE}/", API_BASE = endpoints.api_base))?,
+            )
+            .await?;
     list_of_major_and_minor_releases
         .major_releases
         .sort_by(|rel0, rel1| rel0.release_id.cmp(&rel1.release_id));
-    Ok(list_of_major_and_minor_releases.major_releases)
+
+    let cache_file = major_releases_cache_file(vm_root);
+    let snapshot = MajorReleasesSnapshot {
+        fetched_at,
+        major_releases: list_of_major_and_minor_releases.major_releases,
+    };
+    if let Err(e) = std::fs::create_dir_all(cache_file.parent().unwrap()).and_then(|_| {
+        std::fs::write(
+            &cache_file,
+            serde_json::to_string_pretty(&snapshot).map_err(std::io::Error::from)?,
+        )
+    }) {
+        eprintln!("Could not cache release list to {:?}: {}", cache_file, e);
+    }
+    Ok(snapshot.major_releases)
 }
 
-/// Given a version string resolve various version names to their numerical meanings
-pub fn resolve_version(version: &str, releases: &Vec<MajorReleases>) -> String {
-    // Future work:
-    // - Semver range queries
-    match version {
-        "alpha" => {
-            releases
-                .iter()
-                .rev()
-                .find(|rel| rel.release_status == "Alpha")
-                .cloned()
-                .unwrap()
-                .release_id
-        }
-        "latest" => {
-            releases
-                .iter()
-                .rev()
-                .find(|rel| rel.release_status == "Stable")
-                .cloned()
-                .unwrap()
-                .release_id
-        }
-        "LTS" | "lts" => {
-            releases
+/// Given a version string resolve various version names to their numerical meanings.
+/// Named selectors and ranges resolve to a release series; a fully specified version is
+/// returned as-is.
+pub fn resolve_version(
+    version: &str,
+    releases: &[MajorReleases],
+) -> Result<String, MariaDbVmError> {
+    let release = match version.parse::<VersionSelector>()? {
+        VersionSelector::Alpha => releases
+            .iter()
+            .rev()
+            .find(|rel| rel.release_status == "Alpha"),
+        VersionSelector::Stable => releases
+            .iter()
+            .rev()
+            .find(|rel| rel.release_status == "Stable"),
+        VersionSelector::Lts(n) => releases
+            .iter()
+            .rev()
+            .filter(|rel| rel.release_support_type == "Long Term Support")
+            .nth(n),
+        VersionSelector::Rc => releases.iter().find(|rel| rel.release_status == "RC"),
+        VersionSelector::Range(req) => {
+            if let Some(exact) = req.exact() {
+                return Ok(exact.to_string());
+            }
+            return match releases
                 .iter()
-                .rev()
-                .find(|rel| rel.release_support_type == "Long Term Support")
-                .cloned()
-                .unwrap()
-                .release_id
+                .map(|rel| rel.release_id.clone())
+                .filter(|series| req.matches_series(series))
+                .max()
+            {
+                Some(series) => Ok(series.to_string()),
+                // Without a list of releases, take a plain `major.minor` at its word
+                None if releases.is_empty() => version
+                    .parse::<MariaDbVersion>()
+                    .map(|series| series.to_string())
+                    .map_err(|_| MariaDbVmError::NoMatchingRelease {
+                        selector: version.to_string(),
+                    }),
+                None => Err(MariaDbVmError::NoMatchingRelease {
+                    selector: version.to_string(),
+                }),
+            };
         }
-        "RC" | "rc" => {
-            releases
-                .iter()
-                .filter(|rel| rel.release_status == "RC")
-                .cloned()
-                .next()
-                .unwrap()
-                .release_id
+    };
+    match release {
+        Some(rel) => Ok(rel.release_id.to_string()),
+        None => Err(MariaDbVmError::NoMatchingRelease {
+            selector: version.to_string(),
+        }),
+    }
+}
+
+/// Resolve a version selector all the way to a point release (major.minor.patch), checking
+/// the point releases of each candidate series, newest first
+pub async fn resolve_point_release(
+    version: &str,
+    releases: &[MajorReleases],
+    api_cache: &ApiCache,
+    endpoints: &Endpoints,
+) -> Result<String, MariaDbVmError> {
+    let req = match version.parse::<VersionSelector>()? {
+        VersionSelector::Range(req) => req,
+        _ => VersionReq::series(&resolve_version(version, releases)?.parse()?),
+    };
+    if let Some(exact) = req.exact() {
+        return Ok(exact.to_string());
+    }
+
+    let mut candidates: Vec<MariaDbVersion> = releases
+        .iter()
+        .map(|rel| rel.release_id.clone())
+        .filter(|series| req.matches_series(series))
+        .collect();
+    if candidates.is_empty() {
+        candidates.push(resolve_version(version, releases)?.parse()?);
+    }
+    candidates.sort();
+
+    let client = http_client()?;
+    for series in candidates.iter().rev() {
+        let list_of_point_releases_and_files_root =
+            list_point_releases(&client, api_cache, endpoints, series).await?;
+        if let Some(release) =
+            newest_point_release(&list_of_point_releases_and_files_root, |v| req.matches(v))
+        {
+            return Ok(release.release_id.to_string());
         }
-        s => s.to_string(),
     }
+    Err(MariaDbVmError::NoMatchingRelease {
+        selector: version.to_string(),
+    })
 }
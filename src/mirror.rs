@@ -0,0 +1,239 @@
+use rand::seq::SliceRandom;
+
+use crate::errors::MariaDbVmError;
+use crate::mariadb_vm::{ListAvailableMirrorsRoot, Mirror};
+
+/// How many mirrors a download plan lists, including the first choice
+pub const MAX_MIRROR_ATTEMPTS: usize = 4;
+
+/// How long the `fastest` strategy waits on a mirror before ruling it out
+const PROBE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// How much of a file [`measure_throughput`] downloads
+const THROUGHPUT_SAMPLE_BYTES: u64 = 1024 * 1024;
+
+/// How long [`measure_throughput`] waits for its sample
+const THROUGHPUT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(15);
+
+/// How to order the mirrors a download is attempted from
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MirrorStrategy {
+    #[default]
+    Random,
+    /// Lowest latency to a concurrent HEAD request first
+    Fastest,
+    /// The preference list first, then the rest of the mirror list as listed
+    Ordered,
+}
+
+impl std::str::FromStr for MirrorStrategy {
+    type Err = MariaDbVmError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "random" => Ok(Self::Random),
+            "fastest" => Ok(Self::Fastest),
+            "ordered" => Ok(Self::Ordered),
+            _ => Err(MariaDbVmError::InvalidMirrorStrategy {
+                strategy: s.to_string(),
+            }),
+        }
+    }
+}
+
+impl std::fmt::Display for MirrorStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Random => "random",
+            Self::Fastest => "fastest",
+            Self::Ordered => "ordered",
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MirrorOptions {
+    /// Mirror to try first, e.g., from `download --mirror`
+    pub mirror: Option<String>,
+    pub strategy: MirrorStrategy,
+    /// `mirror_id`s or `mirror_list` country keys, most preferred first
+    pub preference: Vec<String>,
+}
+
+/// The listed mirror with `mirror_id`
+pub fn find_mirror<'a>(
+    mirror_list: &'a ListAvailableMirrorsRoot,
+    mirror_id: &str,
+) -> Result<&'a Mirror, MariaDbVmError> {
+    mirror_list
+        .mirror_list
+        .values()
+        .flatten()
+        .find(|mirror| mirror.mirror_id == mirror_id)
+        .ok_or_else(|| MariaDbVmError::UnknownMirror {
+            mirror_id: mirror_id.to_string(),
+        })
+}
+
+/// The listed mirrors by region, i.e., the country keys of `mirror_list`, in order, leaving
+/// out regions without any
+pub fn regions(mirror_list: &ListAvailableMirrorsRoot) -> Vec<(&str, &[Mirror])> {
+    let mut regions: Vec<(&str, &[Mirror])> = mirror_list
+        .mirror_list
+        .iter()
+        .filter(|(_, mirrors)| !mirrors.is_empty())
+        .map(|(region, mirrors)| (region.as_str(), mirrors.as_slice()))
+        .collect();
+    regions.sort_by_key(|(region, _)| *region);
+    regions
+}
+
+/// `(country, mirror_id)` of every listed mirror, sorted so the order does not depend on
+/// `HashMap` iteration
+fn listed_mirrors(mirror_list: &ListAvailableMirrorsRoot) -> Vec<(String, String)> {
+    let mut mirrors: Vec<(String, String)> = mirror_list
+        .mirror_list
+        .iter()
+        .flat_map(|(country, mirrors)| {
+            mirrors
+                .iter()
+                .map(move |mirror| (country.clone(), mirror.mirror_id.clone()))
+        })
+        .collect();
+    mirrors.sort();
+    mirrors
+}
+
+/// Time a HEAD request to `mirror_url(mirror_id)` for every mirror concurrently.
+/// Fastest first; mirrors that errored or timed out come last, as `None`.
+pub async fn probe_mirrors(
+    client: &reqwest::Client,
+    mirror_ids: &[String],
+    mirror_url: &dyn Fn(&str) -> Result<reqwest::Url, MariaDbVmError>,
+) -> Result<Vec<(String, Option<std::time::Duration>)>, MariaDbVmError> {
+    let mut probes = tokio::task::JoinSet::new();
+    for mirror_id in mirror_ids {
+        let url = mirror_url(mirror_id)?;
+        let client = client.clone();
+        let mirror_id = mirror_id.clone();
+        probes.spawn(async move {
+            let started = std::time::Instant::now();
+            let reachable = client
+                .head(url)
+                .timeout(PROBE_TIMEOUT)
+                .send()
+                .await
+                .is_ok_and(|response| response.status().is_success());
+            (mirror_id, reachable.then(|| started.elapsed()))
+        });
+    }
+
+    let mut latencies: Vec<(String, Option<std::time::Duration>)> =
+        Vec::with_capacity(mirror_ids.len());
+    while let Some(probe) = probes.join_next().await {
+        if let Ok(latency) = probe {
+            latencies.push(latency);
+        }
+    }
+    latencies.sort_by(
+        |(id0, latency0), (id1, latency1)| match (latency0, latency1) {
+            (Some(l0), Some(l1)) => l0.cmp(l1),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => id0.cmp(id1),
+        },
+    );
+    Ok(latencies)
+}
+
+/// Bytes per second downloading the first [`THROUGHPUT_SAMPLE_BYTES`] of `url`;
+/// `None` when the mirror errored or took longer than [`THROUGHPUT_TIMEOUT`]
+pub async fn measure_throughput(client: &reqwest::Client, url: reqwest::Url) -> Option<f64> {
+    let started = std::time::Instant::now();
+    let mut response = client
+        .get(url)
+        .header(
+            reqwest::header::RANGE,
+            format!("bytes=0-{}", THROUGHPUT_SAMPLE_BYTES - 1),
+        )
+        .timeout(THROUGHPUT_TIMEOUT)
+        .send()
+        .await
+        .ok()?
+        .error_for_status()
+        .ok()?;
+    let mut received: u64 = 0;
+    // A server that ignores the range sends the whole file, so stop after the sample
+    while received < THROUGHPUT_SAMPLE_BYTES {
+        match response.chunk().await.ok()? {
+            Some(chunk) => received += chunk.len() as u64,
+            None => break,
+        }
+    }
+    Some(received as f64 / started.elapsed().as_secs_f64())
+}
+
+/// The `mirror_id`s to attempt a download from, in order, at most [`MAX_MIRROR_ATTEMPTS`]
+pub async fn rank_mirrors(
+    client: &reqwest::Client,
+    mirror_list: &ListAvailableMirrorsRoot,
+    options: &MirrorOptions,
+    mirror_url: &dyn Fn(&str) -> Result<reqwest::Url, MariaDbVmError>,
+) -> Result<Vec<String>, MariaDbVmError> {
+    let listed = listed_mirrors(mirror_list);
+    if listed.is_empty() && options.mirror.is_none() {
+        return Err(MariaDbVmError::EmptyMirrorList);
+    }
+    let listed_ids: Vec<String> = listed.iter().map(|(_, id)| id.clone()).collect();
+
+    let mut ranked: Vec<String> = match options.strategy {
+        MirrorStrategy::Random => {
+            let mut ids = listed_ids;
+            ids.shuffle(&mut rand::rng());
+            ids
+        }
+        MirrorStrategy::Fastest => {
+            let latencies = probe_mirrors(client, &listed_ids, mirror_url).await?;
+            if !listed_ids.is_empty() && latencies.iter().all(|(_, latency)| latency.is_none()) {
+                eprintln!("Warning: no mirror answered within {:?}", PROBE_TIMEOUT);
+            }
+            latencies.into_iter().map(|(id, _)| id).collect()
+        }
+        MirrorStrategy::Ordered => {
+            let mut ids: Vec<String> = Vec::with_capacity(listed.len());
+            for preferred in &options.preference {
+                let matching: Vec<&String> = listed
+                    .iter()
+                    .filter(|(country, id)| {
+                        id == preferred || country.eq_ignore_ascii_case(preferred)
+                    })
+                    .map(|(_, id)| id)
+                    .collect();
+                if matching.is_empty() {
+                    eprintln!(
+                        "Warning: preferred mirror {:?} is not in the mirror list",
+                        preferred
+                    );
+                }
+                for id in matching {
+                    if !ids.contains(id) {
+                        ids.push(id.clone());
+                    }
+                }
+            }
+            for id in listed_ids {
+                if !ids.contains(&id) {
+                    ids.push(id);
+                }
+            }
+            ids
+        }
+    };
+
+    if let Some(mirror) = &options.mirror {
+        ranked.retain(|id| id != mirror);
+        ranked.insert(0, mirror.clone());
+    }
+    ranked.truncate(MAX_MIRROR_ATTEMPTS);
+    Ok(ranked)
+}
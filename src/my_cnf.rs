@@ -0,0 +1,134 @@
+use std::path::Path;
+
+use crate::errors::MariaDbVmError;
+use crate::instance::Instance;
+use crate::version::MariaDbVersion;
+
+/// Character set and collation for the codeset of a locale, e.g., `UTF-8` in `en_US.UTF-8`.
+/// Without a codeset (`C`, `POSIX`, `en_US`), and for codesets MariaDB lacks, UTF-8 it is.
+pub fn character_set(locale: &str) -> (&'static str, &'static str) {
+    let codeset: String = locale
+        .split('@')
+        .next()
+        .and_then(|locale| locale.split_once('.'))
+        .map(|(_, codeset)| {
+            codeset
+                .chars()
+                .filter(|c| c.is_ascii_alphanumeric())
+                .collect::<String>()
+                .to_ascii_lowercase()
+        })
+        .unwrap_or_default();
+    match codeset.as_str() {
+        "iso88591" | "iso885915" | "cp1252" => ("latin1", "latin1_swedish_ci"),
+        "iso88592" => ("latin2", "latin2_general_ci"),
+        "iso88597" => ("greek", "greek_general_ci"),
+        "iso88598" => ("hebrew", "hebrew_general_ci"),
+        "iso88599" => ("latin5", "latin5_turkish_ci"),
+        "koi8r" => ("koi8r", "koi8r_general_ci"),
+        "koi8u" => ("koi8u", "koi8u_general_ci"),
+        "cp1251" => ("cp1251", "cp1251_general_ci"),
+        "eucjp" => ("ujis", "ujis_japanese_ci"),
+        "sjis" | "shiftjis" => ("sjis", "sjis_japanese_ci"),
+        "euckr" => ("euckr", "euckr_korean_ci"),
+        "gb2312" => ("gb2312", "gb2312_chinese_ci"),
+        "gbk" => ("gbk", "gbk_chinese_ci"),
+        "big5" => ("big5", "big5_chinese_ci"),
+        _ => ("utf8mb4", "utf8mb4_general_ci"),
+    }
+}
+
+/// The server's option group; servers older than 10.4 read `[mysqld]` rather than `[mariadbd]`
+fn server_group(version: &str) -> &'static str {
+    match version.parse::<MariaDbVersion>() {
+        Ok(version) if version < MariaDbVersion::new(10, 4, None) => "mysqld",
+        _ => "mariadbd",
+    }
+}
+
+/// Option file values with whitespace, quotes, or `#` have to be quoted
+fn value(value: &Path) -> String {
+    let value = value.display().to_string();
+    if value.contains(|c: char| c.is_whitespace() || c == '#' || c == '"' || c == '\'') {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        value
+    }
+}
+
+/// The managed `my.cnf` of `instance`
+pub fn render(instance: &Instance) -> String {
+    let (character_set, collation) = character_set(&instance.locale);
+    let mut my_cnf = format!(
+        "# Managed by {package}; regenerated from its settings, so edits here are lost.
+# Put your own options in a file passed with `--my-cnf-include` instead.
+
+[client]
+port = {port}
+socket = {socket}
+default-character-set = {character_set}
+
+[{server_group}]
+basedir = {basedir}
+datadir = {datadir}
+port = {port}
+bind-address = {hostname}
+socket = {socket}
+pid-file = {pid_file}
+log-error = {log_error}
+character-set-server = {character_set}
+collation-server = {collation}
+",
+        package = env!("CARGO_PKG_NAME"),
+        port = instance.port,
+        socket = value(&instance.socket()),
+        character_set = character_set,
+        server_group = server_group(&instance.version),
+        basedir = value(&instance.prefix),
+        datadir = value(&instance.data_path),
+        hostname = instance.hostname,
+        pid_file = value(&instance.pid_file()),
+        log_error = value(&instance.error_log()),
+        collation = collation,
+    );
+    if !instance.includes.is_empty() {
+        my_cnf.push('\n');
+    }
+    for include in &instance.includes {
+        my_cnf.push_str(&format!(
+            "!{} {}\n",
+            if include.is_dir() {
+                "includedir"
+            } else {
+                "include"
+            },
+            include.display()
+        ));
+    }
+    my_cnf
+}
+
+/// Write the managed `my.cnf` of `instance` to its [`Instance::defaults_file`], unless
+/// it is already up to date. Returns whether it was written.
+pub fn write(instance: &Instance) -> Result<bool, MariaDbVmError> {
+    for include in &instance.includes {
+        if !include.is_absolute() || !include.exists() {
+            return Err(MariaDbVmError::MyCnfIncludeNotFound {
+                path: include.clone(),
+            });
+        }
+    }
+    let my_cnf = render(instance);
+    if std::fs::read_to_string(&instance.defaults_file)
+        .ok()
+        .as_deref()
+        == Some(my_cnf.as_str())
+    {
+        return Ok(false);
+    }
+    if let Some(parent) = instance.defaults_file.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&instance.defaults_file, my_cnf)?;
+    Ok(true)
+}
@@ -0,0 +1,177 @@
+use std::path::Path;
+
+use crate::errors::MariaDbVmError;
+use crate::init::{self, RootAuthentication};
+use crate::instance::Instance;
+use crate::server::server_binary;
+use crate::version::MariaDbVersion;
+
+/// How `service install open-rc` renders the init script and its `conf.d` file
+#[derive(Clone, Debug)]
+pub struct ScriptOptions {
+    /// Account the server runs as
+    pub user: String,
+    pub group: String,
+    /// For `start_pre` to initialise a data directory that has no system tables yet
+    pub root_authentication: RootAuthentication,
+}
+
+/// A shell word in single quotes, which only a single quote ends
+fn quoted(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// The `conf.d` file, which `openrc-run` sources before the init script of the same name:
+/// everything the script needs to know about `instance`
+pub fn render_conf_d(instance: &Instance, options: &ScriptOptions) -> String {
+    // Where a binary tarball of the version has them, for a version not installed yet
+    let older_than = |major: u32, minor: u32| {
+        matches!(instance.version.parse::<MariaDbVersion>(),
+            Ok(version) if version < MariaDbVersion::new(major, minor, None))
+    };
+    let server = server_binary(&instance.prefix).unwrap_or_else(|| {
+        instance.prefix.join("bin").join(if older_than(10, 5) {
+            "mysqld"
+        } else {
+            "mariadbd"
+        })
+    });
+    let install_db = init::install_db_script(&instance.prefix).unwrap_or_else(|_| {
+        instance.prefix.join("scripts").join(if older_than(10, 4) {
+            "mysql_install_db"
+        } else {
+            "mariadb-install-db"
+        })
+    });
+    let init_args =
+        if init::supports_auth_root_authentication_method(&instance.version, &install_db) {
+            format!(
+                "--auth-root-authentication-method={}",
+                options.root_authentication
+            )
+        } else {
+            String::new()
+        };
+    let path = |path: &Path| quoted(&path.display().to_string());
+    format!(
+        "# Managed by {package}; regenerated by `service install open-rc`, so edits here are lost.
+# Put server options in a file passed with `--my-cnf-include` instead.
+
+MARIADB_VERSION={version}
+MARIADB_BASEDIR={basedir}
+MARIADB_SERVER={server}
+MARIADB_DEFAULTS_FILE={defaults_file}
+MARIADB_DATADIR={datadir}
+MARIADB_RUNDIR={rundir}
+MARIADB_LOGDIR={logdir}
+MARIADB_PIDFILE={pid_file}
+MARIADB_USER={user}
+MARIADB_GROUP={group}
+
+# Run by start_pre when MARIADB_DATADIR has no system tables yet
+MARIADB_INSTALL_DB={install_db}
+MARIADB_INSTALL_DB_ARGS={init_args}
+",
+        package = env!("CARGO_PKG_NAME"),
+        version = quoted(&instance.version),
+        basedir = path(&instance.prefix),
+        server = path(&server),
+        defaults_file = path(&instance.defaults_file),
+        datadir = path(&instance.data_path),
+        rundir = path(&instance.runtime_path),
+        logdir = path(&instance.logs_path),
+        pid_file = path(&instance.pid_file()),
+        user = quoted(&options.user),
+        group = quoted(&options.group),
+        install_db = path(&install_db),
+        init_args = quoted(&init_args),
+    )
+}
+
+/// The `openrc-run` script; what it runs comes from its `conf.d` file, see [`render_conf_d`]
+pub fn render_init_script() -> String {
+    format!(
+        r#"#!/sbin/openrc-run
+# Managed by {package}; regenerated by `service install open-rc`, so edits here are lost.
+# Settings are in /etc/conf.d/${{RC_SVCNAME}}.
+
+description="MariaDB ${{MARIADB_VERSION}} database server"
+extra_commands="checkconfig"
+extra_started_commands="reload"
+
+command="${{MARIADB_SERVER}}"
+command_args="--defaults-file=${{MARIADB_DEFAULTS_FILE}}"
+command_user="${{MARIADB_USER}}:${{MARIADB_GROUP}}"
+command_background=true
+pidfile="${{MARIADB_PIDFILE}}"
+# mariadbd shuts down cleanly on SIGTERM; SIGKILL could lose committed transactions.
+# InnoDB crash recovery can take a while.
+retry="TERM/900"
+
+depend() {{
+	need localmount
+	use net dns logger
+	after bootmisc
+}}
+
+checkconfig() {{
+	if [ -z "${{MARIADB_DEFAULTS_FILE}}" ] || [ -z "${{MARIADB_DATADIR}}" ]; then
+		eerror "MARIADB_DEFAULTS_FILE and MARIADB_DATADIR have to be set in /etc/conf.d/${{RC_SVCNAME}}"
+		return 1
+	fi
+	if [ ! -x "${{MARIADB_SERVER}}" ]; then
+		eerror "${{MARIADB_SERVER}} is not installed; run \`{package} install ${{MARIADB_VERSION}}\`"
+		return 1
+	fi
+	if [ ! -r "${{MARIADB_DEFAULTS_FILE}}" ]; then
+		eerror "${{MARIADB_DEFAULTS_FILE}} is missing; run \`{package} my-cnf\`"
+		return 1
+	fi
+	if ! "${{MARIADB_SERVER}}" "--defaults-file=${{MARIADB_DEFAULTS_FILE}}" --help --verbose >/dev/null 2>&1; then
+		eerror "${{MARIADB_DEFAULTS_FILE}} has errors; see \`${{MARIADB_SERVER}} --defaults-file=${{MARIADB_DEFAULTS_FILE}} --help --verbose\`"
+		return 1
+	fi
+}}
+
+start_pre() {{
+	checkconfig || return 1
+	checkpath --directory --owner "${{command_user}}" --mode 0755 "${{MARIADB_RUNDIR}}"
+	checkpath --directory --owner "${{command_user}}" --mode 0750 "${{MARIADB_LOGDIR}}"
+	# The system tables are what mariadb-install-db creates
+	if [ ! -d "${{MARIADB_DATADIR}}/mysql" ]; then
+		checkpath --directory --owner "${{command_user}}" --mode 0750 "${{MARIADB_DATADIR}}"
+		ebegin "Initialising ${{MARIADB_DATADIR}}"
+		# shellcheck disable=SC2086
+		"${{MARIADB_INSTALL_DB}}" "--defaults-file=${{MARIADB_DEFAULTS_FILE}}" \
+			"--basedir=${{MARIADB_BASEDIR}}" "--datadir=${{MARIADB_DATADIR}}" \
+			"--user=${{MARIADB_USER}}" ${{MARIADB_INSTALL_DB_ARGS}} >/dev/null
+		eend $? || return 1
+	fi
+}}
+
+reload() {{
+	ebegin "Reloading ${{RC_SVCNAME}}"
+	start-stop-daemon --signal HUP --pidfile "${{pidfile}}"
+	eend $?
+}}
+"#,
+        package = env!("CARGO_PKG_NAME"),
+    )
+}
+
+/// Write `contents` to `path`, executable for the init script. Returns whether it changed.
+pub fn write(path: &Path, contents: &str, executable: bool) -> Result<bool, MariaDbVmError> {
+    if std::fs::read_to_string(path).ok().as_deref() == Some(contents) {
+        return Ok(false);
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, contents)?;
+    #[cfg(unix)]
+    if executable {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(true)
+}
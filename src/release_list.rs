@@ -0,0 +1,249 @@
+use serde::Serialize;
+
+use crate::errors::MariaDbVmError;
+use crate::mariadb_vm::{MajorReleases, PointRelease};
+
+/// `release_status` of a series in the REST API
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReleaseStatus {
+    Stable,
+    Rc,
+    Alpha,
+}
+
+impl ReleaseStatus {
+    fn matches(&self, release: &MajorReleases) -> bool {
+        release.release_status.eq_ignore_ascii_case(match self {
+            Self::Stable => "Stable",
+            Self::Rc => "RC",
+            Self::Alpha => "Alpha",
+        })
+    }
+}
+
+impl std::str::FromStr for ReleaseStatus {
+    type Err = MariaDbVmError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "stable" | "ga" => Ok(Self::Stable),
+            "rc" => Ok(Self::Rc),
+            "alpha" => Ok(Self::Alpha),
+            _ => Err(MariaDbVmError::InvalidReleaseStatus {
+                status: s.to_string(),
+            }),
+        }
+    }
+}
+
+impl std::fmt::Display for ReleaseStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Stable => "stable",
+            Self::Rc => "rc",
+            Self::Alpha => "alpha",
+        })
+    }
+}
+
+/// `release_support_type` of a series in the REST API
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SupportType {
+    /// Long Term Support
+    Lts,
+    /// Short Term Support
+    Sts,
+}
+
+impl SupportType {
+    fn matches(&self, release: &MajorReleases) -> bool {
+        release
+            .release_support_type
+            .eq_ignore_ascii_case(match self {
+                Self::Lts => "Long Term Support",
+                Self::Sts => "Short Term Support",
+            })
+    }
+}
+
+impl std::str::FromStr for SupportType {
+    type Err = MariaDbVmError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "lts" => Ok(Self::Lts),
+            "sts" => Ok(Self::Sts),
+            _ => Err(MariaDbVmError::InvalidSupportType {
+                support: s.to_string(),
+            }),
+        }
+    }
+}
+
+impl std::fmt::Display for SupportType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Lts => "lts",
+            Self::Sts => "sts",
+        })
+    }
+}
+
+/// How `ls-remote` prints its list
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ListFormat {
+    /// Aligned columns, for people
+    #[default]
+    Table,
+    Json,
+    Csv,
+}
+
+impl std::str::FromStr for ListFormat {
+    type Err = MariaDbVmError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "table" => Ok(Self::Table),
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            _ => Err(MariaDbVmError::InvalidOutputFormat {
+                format: s.to_string(),
+                expected: "`table`, `json`, or `csv`",
+            }),
+        }
+    }
+}
+
+/// Which series `ls-remote` lists; all of them by default
+#[derive(Clone, Debug, Default)]
+pub struct ReleaseFilter {
+    pub status: Option<ReleaseStatus>,
+    pub support: Option<SupportType>,
+    /// Drop series past their end of life. Those without a published date are kept.
+    pub not_eol: bool,
+}
+
+impl ReleaseFilter {
+    pub fn matches(&self, release: &MajorReleases, today: chrono::NaiveDate) -> bool {
+        self.status.is_none_or(|status| status.matches(release))
+            && self.support.is_none_or(|support| support.matches(release))
+            && !(self.not_eol && release.is_eol(today) == Some(true))
+    }
+}
+
+/// One row of `ls-remote`: a series, or one of its point releases
+#[derive(Clone, Debug, Serialize)]
+pub struct RemoteRelease {
+    pub version: String,
+    pub name: String,
+    pub status: String,
+    pub support: String,
+    /// Only known for point releases
+    pub release_date: Option<String>,
+    pub eol_date: Option<chrono::NaiveDate>,
+    pub eol: Option<bool>,
+    /// Whether there is a binary for the selected platform; only known for point releases
+    pub binary: Option<bool>,
+}
+
+impl RemoteRelease {
+    pub fn series(release: &MajorReleases, today: chrono::NaiveDate) -> Self {
+        RemoteRelease {
+            version: release.release_id.to_string(),
+            name: release.release_name.clone(),
+            status: release.release_status.clone(),
+            support: release.release_support_type.clone(),
+            release_date: None,
+            eol_date: release.release_eol_date,
+            eol: release.is_eol(today),
+            binary: None,
+        }
+    }
+
+    /// Point releases share the status, support, and end of life of their series
+    pub fn point_release(
+        release: &MajorReleases,
+        point_release: &PointRelease,
+        today: chrono::NaiveDate,
+    ) -> Self {
+        RemoteRelease {
+            version: point_release.release_id.to_string(),
+            name: point_release.release_name.clone(),
+            release_date: Some(point_release.date_of_release.clone()),
+            binary: point_release.has_binary,
+            ..Self::series(release, today)
+        }
+    }
+
+    fn fields(&self) -> [String; 8] {
+        let or_unknown = |value: Option<String>| value.unwrap_or_else(|| String::from("-"));
+        let yes_no = |value: bool| String::from(if value { "yes" } else { "no" });
+        [
+            self.version.clone(),
+            self.name.clone(),
+            self.status.clone(),
+            self.support.clone(),
+            or_unknown(self.release_date.clone()),
+            or_unknown(self.eol_date.map(|date| date.to_string())),
+            or_unknown(self.eol.map(yes_no)),
+            or_unknown(self.binary.map(yes_no)),
+        ]
+    }
+}
+
+const HEADER: [&str; 8] = [
+    "version", "name", "status", "support", "released", "eol_date", "eol", "binary",
+];
+
+/// Render `rows` in `format`, ending with a newline
+pub fn render(rows: &[RemoteRelease], format: ListFormat) -> Result<String, MariaDbVmError> {
+    Ok(match format {
+        ListFormat::Json => serde_json::to_string_pretty(rows)? + "\n",
+        ListFormat::Csv => std::iter::once(HEADER.map(String::from))
+            .chain(rows.iter().map(RemoteRelease::fields))
+            .map(|fields| {
+                fields
+                    .iter()
+                    .map(|field| csv_field(field))
+                    .collect::<Vec<String>>()
+                    .join(",")
+                    + "\n"
+            })
+            .collect(),
+        ListFormat::Table => {
+            let lines: Vec<[String; 8]> = std::iter::once(HEADER.map(|name| name.to_uppercase()))
+                .chain(rows.iter().map(RemoteRelease::fields))
+                .collect();
+            let mut widths = [0_usize; 8];
+            for fields in &lines {
+                for (width, field) in widths.iter_mut().zip(fields.iter()) {
+                    *width = (*width).max(field.chars().count());
+                }
+            }
+            lines
+                .iter()
+                .map(|fields| {
+                    fields
+                        .iter()
+                        .zip(widths.iter())
+                        .map(|(field, width)| format!("{:<width$}", field, width = width))
+                        .collect::<Vec<String>>()
+                        .join("  ")
+                        .trim_end()
+                        .to_string()
+                        + "\n"
+                })
+                .collect()
+        }
+    })
+}
+
+/// Quoted when it holds a comma, quote, or line break (RFC 4180)
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
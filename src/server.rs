@@ -0,0 +1,307 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::errors::MariaDbVmError;
+use crate::init::InitManifest;
+use crate::instance::Instance;
+use crate::my_cnf;
+
+/// Seconds `start` waits for connections to be accepted, and `stop` for the server to exit
+pub const DEFAULT_SERVICE_TIMEOUT_SECS: u64 = 60;
+
+/// How often to check whether the server is up, or gone
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The server binary of `prefix`; `mysqld` before MariaDB 10.5
+pub(crate) fn server_binary(prefix: &Path) -> Option<PathBuf> {
+    find_binary(prefix, &["mariadbd", "mysqld"])
+}
+
+/// The admin client of `prefix`; `mysqladmin` before MariaDB 10.5
+fn admin_binary(prefix: &Path) -> Option<PathBuf> {
+    find_binary(prefix, &["mariadb-admin", "mysqladmin"])
+}
+
+fn find_binary(prefix: &Path, names: &[&str]) -> Option<PathBuf> {
+    names
+        .iter()
+        .flat_map(|name| [name.to_string(), format!("{}.exe", name)])
+        .map(|name| prefix.join("bin").join(name))
+        .find(|binary| binary.is_file())
+}
+
+/// The pid in the instance's pid file, if that process is still running and is this
+/// instance's server; a stale pid file's pid may since have been reused
+pub fn running_pid(instance: &Instance) -> Option<u32> {
+    let pid: u32 = std::fs::read_to_string(instance.pid_file())
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    is_instance_server(instance, pid).then_some(pid)
+}
+
+/// Whether `pid` runs the server binary of `instance.prefix` with its `--defaults-file`
+fn is_instance_server(instance: &Instance, pid: u32) -> bool {
+    if !is_running(pid) {
+        return false;
+    }
+    let server = match server_binary(&instance.prefix) {
+        Some(server) => server,
+        None => instance.prefix.join("bin").join("mariadbd"),
+    };
+    if cfg!(target_os = "linux") {
+        let proc_dir = Path::new("/proc").join(pid.to_string());
+        // A binary replaced by a reinstall since it was started reads as `<path> (deleted)`
+        let exe = match std::fs::read_link(proc_dir.join("exe")) {
+            Ok(exe) => PathBuf::from(
+                exe.to_string_lossy()
+                    .trim_end_matches(" (deleted)")
+                    .to_string(),
+            ),
+            Err(_) => return false,
+        };
+        let defaults_file_arg = instance.defaults_file_arg();
+        return exe == server.canonicalize().unwrap_or(server)
+            && std::fs::read(proc_dir.join("cmdline")).is_ok_and(|cmdline| {
+                cmdline
+                    .split(|&byte| byte == 0)
+                    .any(|arg| arg == defaults_file_arg.as_bytes())
+            });
+    }
+    if cfg!(windows) {
+        // `tasklist` does not show command lines, only the image name
+        let image_name = server
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        return std::process::Command::new("tasklist")
+            .args(["/FI", &format!("PID eq {}", pid), "/FO", "CSV", "/NH"])
+            .output()
+            .is_ok_and(|output| {
+                String::from_utf8_lossy(&output.stdout)
+                    .to_ascii_lowercase()
+                    .starts_with(&format!("\"{}\"", image_name.to_ascii_lowercase()))
+            });
+    }
+    std::process::Command::new("ps")
+        .args(["-o", "args=", "-p", &pid.to_string()])
+        .output()
+        .is_ok_and(|output| {
+            let args = String::from_utf8_lossy(&output.stdout);
+            args.contains(&instance.defaults_file_arg())
+                && args
+                    .split_whitespace()
+                    .next()
+                    .is_some_and(|command| Path::new(command).file_name() == server.file_name())
+        })
+}
+
+fn is_running(pid: u32) -> bool {
+    if cfg!(target_os = "linux") {
+        return Path::new("/proc").join(pid.to_string()).is_dir();
+    }
+    if cfg!(windows) {
+        return std::process::Command::new("tasklist")
+            .args(["/FI", &format!("PID eq {}", pid), "/NH"])
+            .output()
+            .is_ok_and(|output| {
+                String::from_utf8_lossy(&output.stdout).contains(&pid.to_string())
+            });
+    }
+    signal(pid, "0")
+}
+
+/// `kill -<signal> <pid>`; `0` only checks the process exists
+fn signal(pid: u32, signal: &str) -> bool {
+    std::process::Command::new("kill")
+        .arg(format!("-{}", signal))
+        .arg(pid.to_string())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Whether the server accepts connections over its own socket. Not TCP, where another
+/// server on the same port would answer.
+#[cfg(unix)]
+fn accepts_connections(instance: &Instance, _log_start: u64) -> bool {
+    std::os::unix::net::UnixStream::connect(instance.socket()).is_ok()
+}
+
+/// Without sockets, whether the server logged being ready after `log_start`, which it only
+/// does once it has bound its port
+#[cfg(not(unix))]
+fn accepts_connections(instance: &Instance, log_start: u64) -> bool {
+    std::fs::read(instance.error_log()).is_ok_and(|log| {
+        log.get(log_start as usize..)
+            .is_some_and(|logged| String::from_utf8_lossy(logged).contains("ready for connections"))
+    })
+}
+
+/// Last lines of the error log, to show why a server did not come up
+fn error_log_tail(instance: &Instance) -> String {
+    let log = std::fs::read_to_string(instance.error_log()).unwrap_or_default();
+    let lines: Vec<&str> = log.lines().collect();
+    lines[lines.len().saturating_sub(10)..].join("\n")
+}
+
+/// Launch `mariadbd --defaults-file=...` in the background, detached from this process,
+/// and wait up to `timeout` for it to accept connections. Returns its pid.
+pub fn start(instance: &Instance, timeout: Duration) -> Result<u32, MariaDbVmError> {
+    if let Some(pid) = running_pid(instance) {
+        eprintln!("Already running, pid {}", pid);
+        return Ok(pid);
+    }
+    if InitManifest::read(&instance.data_path)?.is_none()
+        && !instance.data_path.join("mysql").is_dir()
+    {
+        return Err(MariaDbVmError::NotInitialised {
+            data_path: instance.data_path.clone(),
+        });
+    }
+    my_cnf::write(instance)?;
+    for dir in [&instance.runtime_path, &instance.logs_path] {
+        std::fs::create_dir_all(dir)?;
+    }
+    let server =
+        server_binary(&instance.prefix).ok_or_else(|| MariaDbVmError::ServerStartFailed {
+            details: format!(
+                "no mariadbd or mysqld under {:?}",
+                instance.prefix.join("bin")
+            ),
+        })?;
+
+    let log_start = std::fs::metadata(instance.error_log()).map_or(0, |log| log.len());
+    // Anything logged before the error log is opened goes there too
+    let log = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(instance.error_log())?;
+    let mut command = std::process::Command::new(&server);
+    command
+        .arg(instance.defaults_file_arg())
+        .stdin(std::process::Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log);
+    // Its own process group, so it outlives this one and the terminal's signals
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+    eprintln!("{:?}", command);
+    let mut child = command.spawn()?;
+    let pid = child.id();
+    // mariadbd writes the same pid once it is up; until then `stop` can find it here
+    std::fs::write(instance.pid_file(), format!("{}\n", pid))?;
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            let _ = std::fs::remove_file(instance.pid_file());
+            return Err(MariaDbVmError::ServerStartFailed {
+                details: format!(
+                    "mariadbd exited with {}; from {:?}:\n{}",
+                    status,
+                    instance.error_log(),
+                    error_log_tail(instance)
+                ),
+            });
+        }
+        if accepts_connections(instance, log_start) {
+            eprintln!(
+                "Started MariaDB {}, pid {}, on {}:{} and {:?}",
+                instance.version,
+                pid,
+                instance.hostname,
+                instance.port,
+                instance.socket()
+            );
+            return Ok(pid);
+        }
+        if Instant::now() >= deadline {
+            return Err(MariaDbVmError::ServerStartFailed {
+                details: format!(
+                    "not accepting connections after {} seconds, still running as pid {}; see {:?}",
+                    timeout.as_secs(),
+                    pid,
+                    instance.error_log()
+                ),
+            });
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Run the admin client against the instance, with its `my.cnf`
+fn admin(instance: &Instance, commands: &[&str]) -> bool {
+    match admin_binary(&instance.prefix) {
+        Some(admin) => std::process::Command::new(admin)
+            .arg(instance.defaults_file_arg())
+            .args(commands)
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success()),
+        None => false,
+    }
+}
+
+/// Shut the server down with `SHUTDOWN`, or else SIGTERM (which it handles the same way),
+/// and wait up to `timeout` for it to exit
+pub fn stop(instance: &Instance, timeout: Duration) -> Result<(), MariaDbVmError> {
+    let pid = match running_pid(instance) {
+        Some(pid) => pid,
+        None => {
+            eprintln!("Not running");
+            return Ok(());
+        }
+    };
+    // The client protocol needs an account it can log in as, which `root` with socket
+    // authentication is not, unless this is run as root
+    if !admin(instance, &["shutdown"]) {
+        let terminated = if cfg!(windows) {
+            std::process::Command::new("taskkill")
+                .args(["/PID", &pid.to_string()])
+                .status()
+                .is_ok_and(|status| status.success())
+        } else {
+            signal(pid, "TERM")
+        };
+        if !terminated && is_instance_server(instance, pid) {
+            return Err(MariaDbVmError::ServerStopFailed {
+                pid,
+                details: String::from("neither SHUTDOWN nor SIGTERM could be sent"),
+            });
+        }
+    }
+    let deadline = Instant::now() + timeout;
+    while is_instance_server(instance, pid) {
+        if Instant::now() >= deadline {
+            return Err(MariaDbVmError::ServerStopFailed {
+                pid,
+                details: format!("still running after {} seconds", timeout.as_secs()),
+            });
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+    // Removed by mariadbd on a clean shutdown
+    let _ = std::fs::remove_file(instance.pid_file());
+    eprintln!("Stopped MariaDB {}, pid {}", instance.version, pid);
+    Ok(())
+}
+
+/// Reload the grant tables and reopen the logs, like `FLUSH PRIVILEGES, LOGS, TABLES`:
+/// with the admin client, or else SIGHUP, which the server takes to mean the same
+pub fn reload(instance: &Instance) -> Result<(), MariaDbVmError> {
+    let pid = running_pid(instance).ok_or_else(|| MariaDbVmError::ServerNotRunning {
+        pid_file: instance.pid_file(),
+    })?;
+    if !admin(
+        instance,
+        &["flush-privileges", "flush-logs", "flush-tables"],
+    ) && (cfg!(windows) || !signal(pid, "HUP"))
+    {
+        return Err(MariaDbVmError::ServerReloadFailed { pid });
+    }
+    eprintln!("Reloaded MariaDB {}, pid {}", instance.version, pid);
+    Ok(())
+}
@@ -0,0 +1,247 @@
+use std::path::{Path, PathBuf};
+
+use crate::errors::MariaDbVmError;
+use crate::instance::Instance;
+use crate::server::server_binary;
+
+/// Upstream's `mariadb.service` value; each table and connection takes a descriptor
+const LIMIT_NOFILE: u32 = 32768;
+
+/// How `service install systemd` renders the unit, and where it goes
+#[derive(Clone, Debug)]
+pub struct UnitOptions {
+    /// Account the server runs as; not set in user units, which run as their owner
+    pub user: String,
+    pub group: String,
+    /// A unit for the per-user service manager, in `~/.config/systemd/user`
+    pub user_unit: bool,
+    /// Render the `@.service` template, in which each path component that is this version
+    /// (i.e., `--app-version`) becomes `%i`, so that `<name>@10.11` and `<name>@11.4` run
+    /// side by side
+    pub template_version: Option<String>,
+}
+
+/// `<name>.service`, or `<name>@.service` for the template, where `<name>` is that of
+/// `service_install_path`
+pub fn unit_file_name(service_install_path: &Path, options: &UnitOptions) -> String {
+    let name = service_install_path
+        .file_name()
+        .map(|file_name| file_name.to_string_lossy())
+        .unwrap_or_default();
+    let name = name.trim_end_matches(".service").trim_end_matches('@');
+    let name = if name.is_empty() {
+        env!("CARGO_PKG_NAME")
+    } else {
+        name
+    };
+    match options.template_version {
+        Some(_) => format!("{}@.service", name),
+        None => format!("{}.service", name),
+    }
+}
+
+/// Where to write the unit: `service_install_path`, with the template's file name, or in
+/// the user unit directory for [`UnitOptions::user_unit`]
+pub fn unit_path(
+    service_install_path: &Path,
+    options: &UnitOptions,
+) -> Result<PathBuf, MariaDbVmError> {
+    let file_name = unit_file_name(service_install_path, options);
+    if options.user_unit {
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+            .ok_or(MariaDbVmError::UserUnitDirUnknown)?;
+        return Ok(config_home.join("systemd").join("user").join(file_name));
+    }
+    Ok(match service_install_path.parent() {
+        Some(parent) => parent.join(file_name),
+        None => PathBuf::from(file_name),
+    })
+}
+
+/// `path` for a unit file, where `%` starts a specifier; in the template, the components
+/// that are `template_version` become `%i`
+fn value(path: &Path, template_version: Option<&str>) -> String {
+    path.components()
+        .map(|component| {
+            let component = component.as_os_str().to_string_lossy();
+            if Some(component.as_ref()) == template_version {
+                String::from("%i")
+            } else {
+                component.replace('%', "%%")
+            }
+        })
+        .collect::<PathBuf>()
+        .display()
+        .to_string()
+}
+
+/// One word of a command line, where `$` would expand a variable, quoted if it has to be
+fn word(value: String) -> String {
+    let value = value.replace('$', "$$");
+    if value.contains(|c: char| c.is_whitespace() || c == '"' || c == '\\') {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        value
+    }
+}
+
+/// `RuntimeDirectory=` is relative to `/run` (or `$XDG_RUNTIME_DIR` for user units), so only
+/// a `--runtime-path` under there can be one
+fn runtime_directory(runtime_path: &Path, user_unit: bool) -> Option<PathBuf> {
+    let base: PathBuf = if user_unit {
+        PathBuf::from(std::env::var_os("XDG_RUNTIME_DIR")?)
+    } else {
+        PathBuf::from("/run")
+    };
+    runtime_path
+        .strip_prefix(base)
+        .ok()
+        .filter(|relative| !relative.as_os_str().is_empty())
+        .map(Path::to_path_buf)
+}
+
+/// Whether the server binary was built with systemd support, and so notifies readiness.
+/// Binaries that are not installed yet are assumed to.
+fn supports_notify(prefix: &Path) -> bool {
+    match server_binary(prefix).and_then(|binary| std::fs::File::open(binary).ok()) {
+        Some(binary) => contains(std::io::BufReader::new(binary), b"sd_notify").unwrap_or(false),
+        None => true,
+    }
+}
+
+/// Whether `reader` has `needle` in it, read a buffer at a time. The last bytes seen are
+/// kept, in case `needle` straddles two buffers.
+fn contains(mut reader: impl std::io::BufRead, needle: &[u8]) -> std::io::Result<bool> {
+    let overlap = needle.len() - 1;
+    let mut seen: Vec<u8> = Vec::with_capacity(2 * overlap);
+    loop {
+        let buffer = reader.fill_buf()?;
+        if buffer.is_empty() {
+            return Ok(false);
+        }
+        seen.extend_from_slice(&buffer[..buffer.len().min(overlap)]);
+        if seen.windows(needle.len()).any(|window| window == needle)
+            || buffer.windows(needle.len()).any(|window| window == needle)
+        {
+            return Ok(true);
+        }
+        if buffer.len() >= overlap {
+            seen.clear();
+            seen.extend_from_slice(&buffer[buffer.len() - overlap..]);
+        } else {
+            // All of a short buffer is in `seen` already
+            seen.drain(..seen.len().saturating_sub(overlap));
+        }
+        let consumed = buffer.len();
+        reader.consume(consumed);
+    }
+}
+
+/// The unit that runs `instance` with its managed `my.cnf`
+pub fn render(instance: &Instance, options: &UnitOptions) -> Result<String, MariaDbVmError> {
+    let path = |path: &Path| value(path, options.template_version.as_deref());
+    if let Some(version) = &options.template_version {
+        if !instance
+            .data_path
+            .components()
+            .any(|component| component.as_os_str() == version.as_str())
+        {
+            return Err(MariaDbVmError::UnitNotTemplatable {
+                version: version.clone(),
+                data_path: instance.data_path.clone(),
+            });
+        }
+    }
+    let notify = supports_notify(&instance.prefix);
+    let server = server_binary(&instance.prefix)
+        .unwrap_or_else(|| instance.prefix.join("bin").join("mariadbd"));
+
+    let mut unit = format!(
+        "# Managed by {package}; regenerated by `service install systemd`, so edits here are lost.
+# Override settings with `systemctl{user_flag} edit` instead.
+
+[Unit]
+Description=MariaDB {version} database server
+Documentation=man:mariadbd(8) https://mariadb.com/kb/en/systemd/
+After=network.target
+
+[Service]
+Type={service_type}
+",
+        package = env!("CARGO_PKG_NAME"),
+        user_flag = if options.user_unit { " --user" } else { "" },
+        version = match options.template_version {
+            Some(_) => "%i",
+            None => instance.version.as_str(),
+        },
+        service_type = if notify { "notify" } else { "simple" },
+    );
+    if !options.user_unit {
+        unit.push_str(&format!("User={}\nGroup={}\n", options.user, options.group));
+    }
+    match runtime_directory(&instance.runtime_path, options.user_unit) {
+        Some(runtime_directory) => unit.push_str(&format!(
+            "RuntimeDirectory={}\nRuntimeDirectoryMode=0755\n",
+            path(&runtime_directory)
+        )),
+        // Outside of `/run`, so not cleaned up by systemd either
+        None => unit.push_str(&format!(
+            "ExecStartPre=/bin/mkdir -p {}\n",
+            word(path(&instance.runtime_path))
+        )),
+    }
+    unit.push_str(&format!(
+        "ExecStart={server} {defaults_file_arg}
+ExecReload=/bin/kill -HUP $MAINPID
+# mariadbd shuts down cleanly on SIGTERM; SIGKILL could lose committed transactions
+KillSignal=SIGTERM
+SendSIGKILL=no
+Restart=on-abort
+RestartSec=5s
+# InnoDB crash recovery can take a while
+TimeoutStartSec=900
+TimeoutStopSec=900
+LimitNOFILE={limit_nofile}
+UMask=007
+",
+        server = word(path(&server)),
+        defaults_file_arg = word(format!("--defaults-file={}", path(&instance.defaults_file))),
+        limit_nofile = LIMIT_NOFILE,
+    ));
+    // The per-user service manager can only sandbox with unprivileged user namespaces
+    if !options.user_unit {
+        unit.push_str(
+            "ProtectSystem=full
+PrivateTmp=true
+PrivateDevices=true
+NoNewPrivileges=true
+",
+        );
+    }
+    unit.push_str(&format!(
+        "
+[Install]
+WantedBy={}
+",
+        if options.user_unit {
+            "default.target"
+        } else {
+            "multi-user.target"
+        }
+    ));
+    Ok(unit)
+}
+
+/// Write `unit` to `path`. Returns whether it changed.
+pub fn write(path: &Path, unit: &str) -> Result<bool, MariaDbVmError> {
+    if std::fs::read_to_string(path).ok().as_deref() == Some(unit) {
+        return Ok(false);
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, unit)?;
+    Ok(true)
+}
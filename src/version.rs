@@ -0,0 +1,337 @@
+use crate::errors::MariaDbVmError;
+
+/// A MariaDB version, either a release series (`10.11`) or a point release (`10.11.7`),
+/// optionally with a pre-release tag (`11.5.0-rc`).
+/// Compares numerically, so `10.11` sorts after `10.6`, and a pre-release sorts before
+/// the release it precedes.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MariaDbVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: Option<u32>,
+    pub pre_release: Option<String>,
+}
+
+impl MariaDbVersion {
+    pub fn new(major: u32, minor: u32, patch: Option<u32>) -> Self {
+        MariaDbVersion {
+            major,
+            minor,
+            patch,
+            pre_release: None,
+        }
+    }
+
+    /// The release series this version belongs to, e.g., `10.11.7` -> `10.11`
+    pub fn series(&self) -> MariaDbVersion {
+        MariaDbVersion::new(self.major, self.minor, None)
+    }
+
+    fn triple(&self) -> (u32, u32, u32) {
+        (self.major, self.minor, self.patch.unwrap_or(0))
+    }
+}
+
+impl Ord for MariaDbVersion {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (&self.pre_release, &other.pre_release) {
+                (None, None) => std::cmp::Ordering::Equal,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (Some(a), Some(b)) => a.cmp(b),
+            })
+    }
+}
+
+impl PartialOrd for MariaDbVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl std::str::FromStr for MariaDbVersion {
+    type Err = MariaDbVmError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || MariaDbVmError::InvalidVersionSelector {
+            selector: s.to_string(),
+        };
+        let (numbers, pre_release) = match s.trim().split_once('-') {
+            Some((numbers, tag)) if !tag.is_empty() => (numbers, Some(tag.to_ascii_lowercase())),
+            Some(_) => return Err(invalid()),
+            None => (s.trim(), None),
+        };
+        let mut parts = numbers.split('.');
+        let mut next_number = |required: bool| -> Result<Option<u32>, MariaDbVmError> {
+            match parts.next() {
+                Some(part) => part.parse::<u32>().map(Some).map_err(|_| invalid()),
+                None if required => Err(invalid()),
+                None => Ok(None),
+            }
+        };
+        let major = next_number(true)?.unwrap_or_default();
+        let minor = next_number(true)?.unwrap_or_default();
+        let patch = next_number(false)?;
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+        Ok(MariaDbVersion {
+            major,
+            minor,
+            patch,
+            pre_release,
+        })
+    }
+}
+
+impl std::fmt::Display for MariaDbVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)?;
+        if let Some(patch) = self.patch {
+            write!(f, ".{}", patch)?;
+        }
+        if let Some(pre_release) = &self.pre_release {
+            write!(f, "-{}", pre_release)?;
+        }
+        Ok(())
+    }
+}
+
+impl serde::Serialize for MariaDbVersion {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for MariaDbVersion {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// What `--app-version` and the `[VERSION]` arguments accept
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VersionSelector {
+    Alpha,
+    /// Newest stable series; `latest` and `stable` are synonyms
+    Stable,
+    /// Newest Long Term Support series when `0`; `lts-1` is the one before that, etc.
+    Lts(usize),
+    Rc,
+    Range(VersionReq),
+}
+
+impl std::str::FromStr for VersionSelector {
+    type Err = MariaDbVmError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let selector = s.trim().to_ascii_lowercase();
+        Ok(match selector.as_str() {
+            "alpha" => Self::Alpha,
+            "latest" | "stable" => Self::Stable,
+            "lts" => Self::Lts(0),
+            "rc" => Self::Rc,
+            _ => {
+                match selector.strip_prefix("lts-") {
+                    Some(n) => Self::Lts(n.parse().map_err(|_| {
+                        MariaDbVmError::InvalidVersionSelector {
+                            selector: s.to_string(),
+                        }
+                    })?),
+                    None => Self::Range(s.parse()?),
+                }
+            }
+        })
+    }
+}
+
+/// A set of comparators which must all hold, e.g., `>=10.6 <11`, `~11.4`, `^10`, `10.11.x`;
+/// or a single point release, which may be a pre-release, e.g., `11.5.0-rc`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VersionReq {
+    /// Inclusive lower bound as (major, minor, patch)
+    lower: Option<(u32, u32, u32)>,
+    /// Exclusive upper bound as (major, minor, patch)
+    upper: Option<(u32, u32, u32)>,
+    /// Set when the requirement is a single, fully specified version
+    exact: Option<MariaDbVersion>,
+}
+
+impl VersionReq {
+    /// Any point release of `series`
+    pub fn series(series: &MariaDbVersion) -> Self {
+        VersionReq {
+            lower: Some((series.major, series.minor, 0)),
+            upper: Some((series.major, series.minor + 1, 0)),
+            exact: None,
+        }
+    }
+
+    /// The one version this requirement allows, if it names a single point release
+    pub fn exact(&self) -> Option<MariaDbVersion> {
+        self.exact.clone()
+    }
+
+    pub fn matches(&self, version: &MariaDbVersion) -> bool {
+        if let Some(exact) = &self.exact {
+            return exact == version;
+        }
+        let v = version.triple();
+        self.lower.is_none_or(|lower| v >= lower) && self.upper.is_none_or(|upper| v < upper)
+    }
+
+    /// Whether any point release of `series` could match
+    pub fn matches_series(&self, series: &MariaDbVersion) -> bool {
+        let first = (series.major, series.minor, 0);
+        let past_last = (series.major, series.minor + 1, 0);
+        self.lower.is_none_or(|lower| lower < past_last)
+            && self.upper.is_none_or(|upper| first < upper)
+    }
+
+    fn intersect(&mut self, lower: Option<(u32, u32, u32)>, upper: Option<(u32, u32, u32)>) {
+        if let Some(lower) = lower {
+            self.lower = Some(self.lower.map_or(lower, |l| l.max(lower)));
+        }
+        if let Some(upper) = upper {
+            self.upper = Some(self.upper.map_or(upper, |u| u.min(upper)));
+        }
+    }
+}
+
+/// `10`, `10.11`, or `10.11.7`; `x`/`*` wildcards end the version early
+#[derive(Clone, Copy)]
+struct PartialVersion {
+    major: u32,
+    minor: Option<u32>,
+    patch: Option<u32>,
+}
+
+impl PartialVersion {
+    fn parse(s: &str) -> Option<Option<PartialVersion>> {
+        let mut numbers: Vec<u32> = Vec::with_capacity(3);
+        for part in s.split('.') {
+            match part {
+                "x" | "X" | "*" => break,
+                _ => numbers.push(part.parse().ok()?),
+            }
+        }
+        Some(match numbers.as_slice() {
+            [] => None,
+            [major] => Some(PartialVersion {
+                major: *major,
+                minor: None,
+                patch: None,
+            }),
+            [major, minor] => Some(PartialVersion {
+                major: *major,
+                minor: Some(*minor),
+                patch: None,
+            }),
+            [major, minor, patch] => Some(PartialVersion {
+                major: *major,
+                minor: Some(*minor),
+                patch: Some(*patch),
+            }),
+            _ => return None,
+        })
+    }
+
+    fn floor(&self) -> (u32, u32, u32) {
+        (self.major, self.minor.unwrap_or(0), self.patch.unwrap_or(0))
+    }
+
+    /// First version past everything this partial version covers
+    fn past(&self) -> (u32, u32, u32) {
+        match (self.minor, self.patch) {
+            (Some(minor), Some(patch)) => (self.major, minor, patch + 1),
+            (Some(minor), None) => (self.major, minor + 1, 0),
+            _ => (self.major + 1, 0, 0),
+        }
+    }
+}
+
+impl std::str::FromStr for VersionReq {
+    type Err = MariaDbVmError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || MariaDbVmError::InvalidVersionSelector {
+            selector: s.to_string(),
+        };
+
+        // Glue operators separated from their version by whitespace, e.g., `>= 10.6`
+        let mut comparators: Vec<String> = Vec::new();
+        let mut pending_op = String::new();
+        for token in s.split(|c: char| c.is_whitespace() || c == ',') {
+            if token.is_empty() {
+                continue;
+            } else if token.chars().all(|c| "<>=~^".contains(c)) {
+                pending_op.push_str(token);
+            } else {
+                comparators.push(format!("{}{}", pending_op, token));
+                pending_op.clear();
+            }
+        }
+        if comparators.is_empty() || !pending_op.is_empty() {
+            return Err(invalid());
+        }
+
+        let mut req = VersionReq {
+            lower: None,
+            upper: None,
+            exact: None,
+        };
+        for comparator in &comparators {
+            let split_at = comparator
+                .find(|c: char| !"<>=~^".contains(c))
+                .unwrap_or(comparator.len());
+            let (op, version) = comparator.split_at(split_at);
+            // Only a single, exact version can be a pre-release, e.g., `11.5.0-rc`
+            let (version, pre_release) = match version.split_once('-') {
+                Some((version, tag)) if !tag.is_empty() => {
+                    (version, Some(tag.to_ascii_lowercase()))
+                }
+                Some(_) => return Err(invalid()),
+                None => (version, None),
+            };
+            let version = match PartialVersion::parse(version).ok_or_else(invalid)? {
+                Some(version) => version,
+                None if pre_release.is_none() && (op.is_empty() || op == "=") => continue,
+                None => return Err(invalid()),
+            };
+            let single = comparators.len() == 1 && version.patch.is_some();
+            if pre_release.is_some() && !(single && (op.is_empty() || op == "=")) {
+                return Err(invalid());
+            }
+            match op {
+                "" | "=" => {
+                    if single {
+                        req.exact = Some(MariaDbVersion {
+                            major: version.major,
+                            minor: version.minor.unwrap_or(0),
+                            patch: version.patch,
+                            pre_release,
+                        });
+                    }
+                    req.intersect(Some(version.floor()), Some(version.past()))
+                }
+                ">=" => req.intersect(Some(version.floor()), None),
+                ">" => req.intersect(Some(version.past()), None),
+                "<" => req.intersect(None, Some(version.floor())),
+                "<=" => req.intersect(None, Some(version.past())),
+                "~" => req.intersect(
+                    Some(version.floor()),
+                    Some(match version.minor {
+                        Some(minor) => (version.major, minor + 1, 0),
+                        None => (version.major + 1, 0, 0),
+                    }),
+                ),
+                "^" => req.intersect(Some(version.floor()), Some((version.major + 1, 0, 0))),
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(req)
+    }
+}
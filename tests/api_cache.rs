@@ -0,0 +1,119 @@
+//! `ApiCache` revalidation and fallback, and the release list it feeds, against a local
+//! stand-in for the REST API
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use mariadb_version_manager_rs::api_cache::ApiCache;
+use mariadb_version_manager_rs::mariadb_vm::{self, Endpoints};
+
+mod common;
+use common::{scratch, MockServer, Response};
+
+const RELEASES: &[u8] = br#"{"major_releases": [{
+    "release_id": "11.4",
+    "release_name": "MariaDB Server 11.4",
+    "release_status": "Stable",
+    "release_support_type": "Long Term Support",
+    "release_eol_date": "2029-05-29"
+}]}"#;
+
+/// Serves `RELEASES` with an `ETag`, answering a matching `If-None-Match` with 304
+fn revalidating_server() -> MockServer {
+    MockServer::start(|request| match request.header("if-none-match") {
+        Some("\"v1\"") => Response::new(304, b""),
+        _ => Response::new(200, RELEASES).header("ETag", "\"v1\""),
+    })
+}
+
+/// Serves `RELEASES` once, then fails
+fn failing_server() -> MockServer {
+    let served = Arc::new(AtomicUsize::new(0));
+    MockServer::start(move |_| match served.fetch_add(1, Ordering::SeqCst) {
+        0 => Response::new(200, RELEASES),
+        _ => Response::new(500, b"down for maintenance"),
+    })
+}
+
+fn cache(name: &str, ttl: Duration, offline: bool) -> ApiCache {
+    ApiCache::new(&scratch(name).into_os_string(), ttl, offline)
+}
+
+fn url(server: &MockServer) -> reqwest::Url {
+    reqwest::Url::parse(&format!("{}/", server.url)).unwrap()
+}
+
+#[tokio::test]
+async fn serves_fresh_entries_from_the_cache() {
+    let server = revalidating_server();
+    let cache = cache("api-cache-fresh", Duration::from_secs(3600), false);
+    let client = mariadb_vm::http_client().unwrap();
+
+    let (body, fetched_at) = cache.get(&client, url(&server)).await.unwrap();
+    let (cached_body, cached_fetched_at) = cache.get(&client, url(&server)).await.unwrap();
+    assert_eq!(body, RELEASES);
+    assert_eq!(cached_body, RELEASES);
+    assert_eq!(cached_fetched_at, fetched_at);
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn revalidates_expired_entries_with_the_etag() {
+    let server = revalidating_server();
+    let cache = cache("api-cache-etag", Duration::ZERO, false);
+    let client = mariadb_vm::http_client().unwrap();
+
+    let (_, fetched_at) = cache.get(&client, url(&server)).await.unwrap();
+    let (body, revalidated_at) = cache.get(&client, url(&server)).await.unwrap();
+    assert_eq!(body, RELEASES);
+    assert!(revalidated_at > fetched_at);
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].header("if-none-match"), None);
+    assert_eq!(requests[1].header("if-none-match"), Some("\"v1\""));
+}
+
+#[tokio::test]
+async fn falls_back_to_the_stale_entry_with_its_timestamp() {
+    let server = failing_server();
+    let cache = cache("api-cache-stale", Duration::ZERO, false);
+    let client = mariadb_vm::http_client().unwrap();
+
+    let (_, fetched_at) = cache.get(&client, url(&server)).await.unwrap();
+    let (body, stale_fetched_at) = cache.get(&client, url(&server)).await.unwrap();
+    assert_eq!(body, RELEASES);
+    assert_eq!(stale_fetched_at, fetched_at);
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn offline_uses_only_the_cache() {
+    let server = revalidating_server();
+    let cache = cache("api-cache-offline", Duration::ZERO, true);
+    let client = mariadb_vm::http_client().unwrap();
+
+    assert!(cache.get(&client, url(&server)).await.is_err());
+    assert!(server.requests().is_empty());
+}
+
+#[tokio::test]
+async fn the_release_list_keeps_when_it_was_fetched() {
+    let server = failing_server();
+    let vm_root = scratch("api-cache-release-list").into_os_string();
+    let cache = ApiCache::new(&vm_root, Duration::ZERO, false);
+    let endpoints = Endpoints::new(Some(server.url.clone()), None, None);
+
+    mariadb_vm::versions_from_remote(&vm_root, &cache, &endpoints)
+        .await
+        .unwrap();
+    let (first, _) = mariadb_vm::offline_major_releases(&vm_root).unwrap();
+    // Served from the stale entry, which is no newer than before
+    let releases = mariadb_vm::versions_from_remote(&vm_root, &cache, &endpoints)
+        .await
+        .unwrap();
+    let (second, cache_file) = mariadb_vm::offline_major_releases(&vm_root).unwrap();
+    assert_eq!(releases.len(), 1);
+    assert!(cache_file.is_some());
+    assert_eq!(second.fetched_at, first.fetched_at);
+}
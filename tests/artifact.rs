@@ -0,0 +1,244 @@
+//! MariaDB's `cpu` labels for `--arch`, and picking a release's file with `ArtifactSelector`
+
+use mariadb_version_manager_rs::artifact::{mariadb_cpu, ArtifactSelector, PackageType, Variant};
+use mariadb_version_manager_rs::mariadb_vm::Files;
+
+#[test]
+fn maps_rust_architectures_to_mariadb_labels() {
+    assert_eq!(mariadb_cpu("x86_64"), "x86_64");
+    assert_eq!(mariadb_cpu("i686"), "x86");
+    assert_eq!(mariadb_cpu("arm64"), "aarch64");
+    assert_eq!(mariadb_cpu("aarch64"), "aarch64");
+    assert_eq!(mariadb_cpu("powerpc64"), "ppc64");
+    assert_eq!(mariadb_cpu("powerpc64le"), "ppc64le");
+    assert_eq!(mariadb_cpu("s390x"), "s390x");
+}
+
+#[test]
+fn passes_mariadb_labels_through() {
+    assert_eq!(mariadb_cpu("ppc64le"), "ppc64le");
+    assert_eq!(mariadb_cpu("PPC64"), "ppc64");
+}
+
+/// `(file_name, os, cpu, package_type)`, as the REST API lists a release's files
+const RELEASE_FILES: &[(&str, &str, &str, &str)] = &[
+    ("mariadb-11.4.2.tar.gz", "Source", "", "source"),
+    (
+        "mariadb-11.4.2-linux-x86_64.tar.gz",
+        "Linux",
+        "x86_64",
+        "gzipped tar file",
+    ),
+    (
+        "mariadb-11.4.2-linux-systemd-x86_64.tar.gz",
+        "Linux",
+        "x86_64",
+        "gzipped tar file",
+    ),
+    (
+        "mariadb-11.4.2-linux-systemd-x86_64-debug.tar.gz",
+        "Linux",
+        "x86_64",
+        "gzipped tar file",
+    ),
+    (
+        "mariadb-11.4.2-linux-x86_64-musl.tar.gz",
+        "Linux",
+        "x86_64",
+        "gzipped tar file",
+    ),
+    (
+        "mariadb-11.4.2-linux-systemd-aarch64.tar.gz",
+        "Linux",
+        "aarch64",
+        "gzipped tar file",
+    ),
+    (
+        "MariaDB-server-11.4.2-1.el9.x86_64.rpm",
+        "Linux",
+        "x86_64",
+        "RPM package",
+    ),
+    (
+        "MariaDB-server-debuginfo-11.4.2-1.el9.x86_64.rpm",
+        "Linux",
+        "x86_64",
+        "RPM package",
+    ),
+    (
+        "mariadb-server_11.4.2+maria~deb12_amd64.deb",
+        "Linux",
+        "x86_64",
+        "DEB package",
+    ),
+    (
+        "mariadb-11.4.2-winx64-debugsymbols.zip",
+        "Windows",
+        "x86_64",
+        "ZIP file",
+    ),
+    ("mariadb-11.4.2-winx64.zip", "Windows", "x86_64", "ZIP file"),
+    (
+        "mariadb-11.4.2-winx64.msi",
+        "Windows",
+        "x86_64",
+        "MSI package",
+    ),
+];
+
+fn release_files() -> Vec<Files> {
+    RELEASE_FILES
+        .iter()
+        .enumerate()
+        .map(|(file_id, (file_name, os, cpu, package_type))| {
+            serde_json::from_value(serde_json::json!({
+                "file_id": file_id,
+                "file_name": file_name,
+                "package_type": package_type,
+                "os": os,
+                "cpu": if cpu.is_empty() { None } else { Some(cpu) },
+                "checksum": {},
+                "signature": null,
+                "checksum_url": "",
+                "signature_url": "",
+                "file_download_url": ""
+            }))
+            .unwrap()
+        })
+        .collect()
+}
+
+/// For `package_type`, on the OS that publishes it
+fn selector(cpu: &str, package_type: PackageType, variants: &[Variant]) -> ArtifactSelector {
+    ArtifactSelector {
+        os: String::from(match package_type {
+            PackageType::Zip | PackageType::Msi => "Windows",
+            PackageType::Source => "Source",
+            _ => "Linux",
+        }),
+        cpu: mariadb_cpu(cpu),
+        package_type,
+        variants: variants.to_vec(),
+    }
+}
+
+#[test]
+fn selects_the_file_for_the_platform_package_type_and_variant() {
+    use PackageType::*;
+    use Variant::*;
+
+    let files = release_files();
+    let cases: &[(&str, PackageType, &[Variant], Option<&str>)] = &[
+        // systemd is preferred, the other variants are only had by asking
+        (
+            "x86_64",
+            Bintar,
+            &[],
+            Some("mariadb-11.4.2-linux-systemd-x86_64.tar.gz"),
+        ),
+        (
+            "x86_64",
+            Bintar,
+            &[Systemd],
+            Some("mariadb-11.4.2-linux-systemd-x86_64.tar.gz"),
+        ),
+        (
+            "x86_64",
+            Bintar,
+            &[NonSystemd],
+            Some("mariadb-11.4.2-linux-x86_64.tar.gz"),
+        ),
+        (
+            "x86_64",
+            Bintar,
+            &[Musl],
+            Some("mariadb-11.4.2-linux-x86_64-musl.tar.gz"),
+        ),
+        ("x86_64", Bintar, &[Systemd, Musl], None),
+        (
+            "x86_64",
+            Bintar,
+            &[Debug],
+            Some("mariadb-11.4.2-linux-systemd-x86_64-debug.tar.gz"),
+        ),
+        (
+            "aarch64",
+            Bintar,
+            &[],
+            Some("mariadb-11.4.2-linux-systemd-aarch64.tar.gz"),
+        ),
+        (
+            "arm64",
+            Bintar,
+            &[],
+            Some("mariadb-11.4.2-linux-systemd-aarch64.tar.gz"),
+        ),
+        ("aarch64", Bintar, &[NonSystemd], None),
+        ("ppc64le", Bintar, &[], None),
+        (
+            "x86_64",
+            Rpm,
+            &[],
+            Some("MariaDB-server-11.4.2-1.el9.x86_64.rpm"),
+        ),
+        (
+            "x86_64",
+            Rpm,
+            &[Debug],
+            Some("MariaDB-server-debuginfo-11.4.2-1.el9.x86_64.rpm"),
+        ),
+        (
+            "x86_64",
+            Deb,
+            &[],
+            Some("mariadb-server_11.4.2+maria~deb12_amd64.deb"),
+        ),
+        ("x86_64", Zip, &[], Some("mariadb-11.4.2-winx64.zip")),
+        (
+            "x86_64",
+            Zip,
+            &[Debug],
+            Some("mariadb-11.4.2-winx64-debugsymbols.zip"),
+        ),
+        ("x86_64", Msi, &[], Some("mariadb-11.4.2-winx64.msi")),
+        ("x86", Zip, &[], None),
+        ("x86_64", Source, &[], Some("mariadb-11.4.2.tar.gz")),
+        ("aarch64", Source, &[], Some("mariadb-11.4.2.tar.gz")),
+    ];
+    for (cpu, package_type, variants, expected) in cases {
+        let selector = selector(cpu, *package_type, variants);
+        assert_eq!(
+            selector.select(&files).map(|file| file.file_name.as_str()),
+            *expected,
+            "{}",
+            selector
+        );
+    }
+}
+
+#[test]
+fn downloads_a_zip_on_windows_unless_asked_otherwise() {
+    assert_eq!(PackageType::default_for("Windows"), PackageType::Zip);
+    assert_eq!(PackageType::default_for("Linux"), PackageType::Bintar);
+    assert_eq!(PackageType::default_for("Source"), PackageType::Source);
+
+    // A package type only Windows has picks Windows, from any host
+    let selector =
+        ArtifactSelector::new(Some("x86_64"), Some(PackageType::Zip), Vec::new()).unwrap();
+    assert_eq!(selector.os, "Windows");
+    let selector =
+        ArtifactSelector::new(Some("x86_64"), Some(PackageType::Rpm), Vec::new()).unwrap();
+    assert_eq!(selector.os, "Linux");
+}
+
+#[test]
+fn knows_which_operating_systems_are_listed() {
+    let files = release_files();
+    assert!(selector("x86_64", PackageType::Bintar, &[]).os_is_listed(&files));
+    assert!(selector("x86_64", PackageType::Source, &[]).os_is_listed(&files));
+    let freebsd = ArtifactSelector {
+        os: String::from("FreeBSD"),
+        ..selector("x86_64", PackageType::Bintar, &[])
+    };
+    assert!(!freebsd.os_is_listed(&files));
+}
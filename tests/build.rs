@@ -0,0 +1,165 @@
+//! Source builds with `build::build_and_install`, against a stand-in `cmake` that records how
+//! it was run
+
+use std::path::Path;
+
+use mariadb_version_manager_rs::build::{self, BuildOptions, BuildPreset};
+
+mod common;
+use common::scratch;
+
+fn options(preset: BuildPreset, cmake_args: &[&str], jobs: Option<usize>) -> BuildOptions {
+    BuildOptions {
+        preset,
+        cmake_args: cmake_args.iter().map(|arg| arg.to_string()).collect(),
+        jobs,
+    }
+}
+
+#[test]
+fn cmake_args_come_after_the_preset() {
+    assert_eq!(
+        options(BuildPreset::Default, &["-DWITH_SSL=system"], None).configure_args(),
+        vec!["-DCMAKE_BUILD_TYPE=RelWithDebInfo", "-DWITH_SSL=system"]
+    );
+    let configure_args =
+        options(BuildPreset::Minimal, &["-DPLUGIN_CONNECT=YES"], None).configure_args();
+    assert_eq!(configure_args[0], "-DCMAKE_BUILD_TYPE=RelWithDebInfo");
+    assert_eq!(&configure_args[1..11], BuildPreset::Minimal.cmake_args());
+    // Last, so it overrides the preset's -DPLUGIN_CONNECT=NO
+    assert_eq!(configure_args[11..], ["-DPLUGIN_CONNECT=YES"]);
+}
+
+#[test]
+fn caches_by_version_options_and_prefix_but_not_jobs() {
+    let minimal = options(BuildPreset::Minimal, &[], Some(2));
+    let key = minimal.cache_key("11.4.2", Path::new("/opt/mariadb"));
+    assert!(key.starts_with("11.4.2-"));
+    assert_eq!(
+        key,
+        options(BuildPreset::Minimal, &[], Some(8)).cache_key("11.4.2", Path::new("/opt/mariadb"))
+    );
+    for other in [
+        minimal.cache_key("11.4.3", Path::new("/opt/mariadb")),
+        minimal.cache_key("11.4.2", Path::new("/opt/mariadb-11.4")),
+        options(BuildPreset::Default, &[], Some(2)).cache_key("11.4.2", Path::new("/opt/mariadb")),
+        options(BuildPreset::Minimal, &["-DWITH_SSL=system"], Some(2))
+            .cache_key("11.4.2", Path::new("/opt/mariadb")),
+    ] {
+        assert_ne!(key, other);
+    }
+}
+
+/// Logs its arguments to `cmake.log` next to it. Configuring records the install prefix in
+/// the build directory; installing puts a `bin/mariadbd` under `$DESTDIR<prefix>`.
+#[cfg(unix)]
+const FAKE_CMAKE: &str = r#"#!/bin/sh
+echo "$*" >> "$(dirname "$0")/cmake.log"
+case "$1" in
+-S)
+    mkdir -p "$4"
+    for arg; do
+        case "$arg" in -DCMAKE_INSTALL_PREFIX=*) echo "${arg#*=}" > "$4/prefix" ;; esac
+    done
+    ;;
+--install)
+    prefix=$(cat "$2/prefix")
+    mkdir -p "$DESTDIR$prefix/bin"
+    echo built > "$DESTDIR$prefix/bin/mariadbd"
+    ;;
+esac
+"#;
+
+#[cfg(unix)]
+fn source_archive(dir: &Path) -> std::ffi::OsString {
+    let path = dir.join("mariadb-11.4.2.tar.gz");
+    let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+        std::fs::File::create(&path).unwrap(),
+        flate2::Compression::fast(),
+    ));
+    let mut header = tar::Header::new_gnu();
+    header.set_size(0);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder
+        .append_data(&mut header, "mariadb-11.4.2/CMakeLists.txt", &b""[..])
+        .unwrap();
+    builder.into_inner().unwrap().finish().unwrap();
+    path.into_os_string()
+}
+
+#[cfg(unix)]
+#[test]
+fn builds_with_cmake_stages_with_destdir_and_reuses_the_build() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = scratch("build-cmake");
+    let bin = dir.join("bin");
+    std::fs::create_dir_all(&bin).unwrap();
+    std::fs::write(bin.join("cmake"), FAKE_CMAKE).unwrap();
+    std::fs::set_permissions(bin.join("cmake"), std::fs::Permissions::from_mode(0o755)).unwrap();
+    // Only this test runs `cmake`
+    std::env::set_var(
+        "PATH",
+        std::env::join_paths(std::iter::once(bin.clone()).chain(std::env::split_paths(
+            &std::env::var_os("PATH").unwrap_or_default(),
+        )))
+        .unwrap(),
+    );
+
+    let archive = source_archive(&dir);
+    let prefix = dir.join("prefix");
+    let vm_root = dir.join("vm");
+    let options = options(BuildPreset::Minimal, &["-DPLUGIN_CONNECT=YES"], Some(3));
+    let build = || {
+        build::build_and_install(
+            &archive,
+            "11.4.2",
+            &prefix.clone().into_os_string(),
+            &dir.join("root").into_os_string(),
+            &vm_root.clone().into_os_string(),
+            &options,
+        )
+        .unwrap()
+    };
+
+    let manifest = build();
+    assert_eq!(manifest.prefix, prefix);
+    assert_eq!(manifest.build_options, Some(options.configure_args()));
+    assert_eq!(
+        std::fs::read_to_string(prefix.join("bin/mariadbd")).unwrap(),
+        "built\n"
+    );
+
+    let log = std::fs::read_to_string(bin.join("cmake.log")).unwrap();
+    let runs: Vec<&str> = log.lines().collect();
+    assert_eq!(runs.len(), 3, "{}", log);
+    assert!(runs[0].contains(&format!(
+        "-DCMAKE_INSTALL_PREFIX={} {}",
+        prefix.display(),
+        options.configure_args().join(" ")
+    )));
+    assert!(runs[1].starts_with("--build ") && runs[1].ends_with(" --parallel 3"));
+    assert!(runs[2].starts_with("--install "));
+
+    // Staged under the cache with DESTDIR, then copied
+    let builds = vm_root.join("cache/mariadb-builds");
+    let key = options.cache_key("11.4.2", &prefix);
+    assert!(builds.join(format!("{}.complete", key)).is_file());
+    assert!(builds
+        .join(&key)
+        .join(prefix.strip_prefix("/").unwrap())
+        .join("bin/mariadbd")
+        .is_file());
+    assert!(!builds.join(format!("{}.work", key)).exists());
+
+    std::fs::write(prefix.join("bin/leftover"), b"").unwrap();
+    build();
+    assert_eq!(
+        std::fs::read_to_string(bin.join("cmake.log")).unwrap(),
+        log,
+        "built again"
+    );
+    assert!(prefix.join("bin/mariadbd").is_file());
+    assert!(!prefix.join("bin/leftover").exists());
+}
@@ -0,0 +1,148 @@
+//! Resuming downloads with `mariadb_vm::download_file`, against a server that may or may not
+//! honour ranges
+
+use mariadb_version_manager_rs::mariadb_vm::{self, Checksum};
+use sha2::Digest;
+
+mod common;
+use common::{scratch, MockServer, Response};
+
+fn body() -> Vec<u8> {
+    (0..4096_u32).flat_map(|i| i.to_le_bytes()).collect()
+}
+
+fn checksum_of(body: &[u8]) -> Checksum {
+    Checksum {
+        md5sum: None,
+        sha1sum: None,
+        sha256sum: Some(hex::encode(sha2::Sha256::digest(body))),
+        sha512sum: None,
+    }
+}
+
+/// Serves `body`, honouring `Range: bytes=<from>-` if `ranges`
+fn archive_server(body: Vec<u8>, ranges: bool) -> MockServer {
+    MockServer::start(move |request| {
+        match request
+            .header("range")
+            .and_then(|range| range.strip_prefix("bytes="))
+            .and_then(|range| range.trim_end_matches('-').parse::<usize>().ok())
+        {
+            Some(from) if ranges && from >= body.len() => Response::new(416, b""),
+            Some(from) if ranges => Response::new(206, &body[from..]).header(
+                "Content-Range",
+                &format!("bytes {}-{}/{}", from, body.len() - 1, body.len()),
+            ),
+            _ => Response::new(200, &body),
+        }
+    })
+}
+
+async fn download(
+    server_url: &str,
+    target: &std::path::Path,
+    checksum: &Checksum,
+) -> Result<(), String> {
+    mariadb_vm::download_file(
+        &mariadb_vm::http_client().unwrap(),
+        reqwest::Url::parse(&format!("{}/mariadb.tar.gz", server_url)).unwrap(),
+        target,
+        checksum,
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+fn part_of(target: &std::path::Path) -> std::path::PathBuf {
+    target.with_file_name("mariadb.tar.gz.part")
+}
+
+#[tokio::test]
+async fn resumes_with_206() {
+    let dir = scratch("download-206");
+    let target = dir.join("mariadb.tar.gz");
+    std::fs::write(part_of(&target), &body()[..1000]).unwrap();
+    let server = archive_server(body(), true);
+
+    download(&server.url, &target, &checksum_of(&body()))
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&target).unwrap(), body());
+    assert!(!part_of(&target).exists());
+    assert_eq!(server.requests()[0].header("range"), Some("bytes=1000-"));
+}
+
+#[tokio::test]
+async fn restarts_when_the_range_is_ignored() {
+    let dir = scratch("download-200");
+    let target = dir.join("mariadb.tar.gz");
+    // Not what the server has, so appending to it would fail the checksum
+    std::fs::write(part_of(&target), vec![b'x'; 1000]).unwrap();
+    let server = archive_server(body(), false);
+
+    download(&server.url, &target, &checksum_of(&body()))
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&target).unwrap(), body());
+    assert!(!part_of(&target).exists());
+}
+
+#[tokio::test]
+async fn completes_a_whole_part_file_on_416() {
+    let dir = scratch("download-416");
+    let target = dir.join("mariadb.tar.gz");
+    std::fs::write(part_of(&target), body()).unwrap();
+    let server = archive_server(body(), true);
+
+    download(&server.url, &target, &checksum_of(&body()))
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&target).unwrap(), body());
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn deletes_the_part_file_on_checksum_mismatch() {
+    let dir = scratch("download-mismatch");
+    let target = dir.join("mariadb.tar.gz");
+    let server = archive_server(body(), true);
+
+    assert!(
+        download(&server.url, &target, &checksum_of(b"something else"))
+            .await
+            .is_err()
+    );
+    assert!(!target.exists());
+    assert!(!part_of(&target).exists());
+}
+
+#[tokio::test]
+async fn keeps_the_part_file_when_the_resume_request_fails() {
+    let dir = scratch("download-500");
+    let target = dir.join("mariadb.tar.gz");
+    std::fs::write(part_of(&target), &body()[..1000]).unwrap();
+    let server = MockServer::start(|_| Response::new(500, b"oops"));
+
+    assert!(download(&server.url, &target, &checksum_of(&body()))
+        .await
+        .is_err());
+    assert_eq!(std::fs::read(part_of(&target)).unwrap(), &body()[..1000]);
+    assert!(!target.exists());
+}
+
+#[tokio::test]
+async fn keeps_the_part_file_when_the_server_is_unreachable() {
+    let dir = scratch("download-unreachable");
+    let target = dir.join("mariadb.tar.gz");
+    std::fs::write(part_of(&target), &body()[..1000]).unwrap();
+    // Bound, then released, so nothing listens there
+    let url = {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("http://{}", listener.local_addr().unwrap())
+    };
+
+    assert!(download(&url, &target, &checksum_of(&body()))
+        .await
+        .is_err());
+    assert_eq!(std::fs::read(part_of(&target)).unwrap(), &body()[..1000]);
+}
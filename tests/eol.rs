@@ -0,0 +1,156 @@
+//! `EolPolicy`: how near its end of life a series is, on a fixed day, and when that fails
+//! with `--fail-on-eol`
+
+use mariadb_version_manager_rs::eol::{EolPolicy, EolStatus};
+use mariadb_version_manager_rs::mariadb_vm::MajorReleases;
+
+fn date(year: i32, month: u32, day: u32) -> chrono::NaiveDate {
+    chrono::NaiveDate::from_ymd_opt(year, month, day).unwrap()
+}
+
+fn today() -> chrono::NaiveDate {
+    date(2026, 1, 15)
+}
+
+fn series(release_id: &str, eol_date: Option<chrono::NaiveDate>) -> MajorReleases {
+    MajorReleases {
+        release_id: release_id.parse().unwrap(),
+        release_name: format!("MariaDB {}", release_id),
+        release_status: String::from("Stable"),
+        release_support_type: String::from("Long Term Support"),
+        release_eol_date: eol_date,
+    }
+}
+
+fn policy(warning_days: u32, fail_on_eol: bool) -> EolPolicy {
+    EolPolicy {
+        warning_days,
+        fail_on_eol,
+    }
+}
+
+#[test]
+fn warns_within_the_window_and_not_before() {
+    let policy = policy(90, false);
+    let status = |eol_date| policy.status(Some(&series("10.11", eol_date)), today());
+
+    let eol_date = today() + chrono::Days::new(91);
+    assert_eq!(
+        status(Some(eol_date)),
+        EolStatus::Supported {
+            eol_date: Some(eol_date)
+        }
+    );
+    let eol_date = today() + chrono::Days::new(90);
+    assert_eq!(
+        status(Some(eol_date)),
+        EolStatus::NearEol {
+            eol_date,
+            days_left: 90
+        }
+    );
+    let eol_date = today() + chrono::Days::new(1);
+    assert_eq!(
+        status(Some(eol_date)),
+        EolStatus::NearEol {
+            eol_date,
+            days_left: 1
+        }
+    );
+    // On the day itself it is over
+    assert_eq!(status(Some(today())), EolStatus::Eol { eol_date: today() });
+    assert_eq!(
+        status(Some(date(2025, 6, 24))),
+        EolStatus::Eol {
+            eol_date: date(2025, 6, 24)
+        }
+    );
+    assert_eq!(status(None), EolStatus::Supported { eol_date: None });
+    assert_eq!(policy.status(None, today()), EolStatus::Unknown);
+}
+
+#[test]
+fn the_window_is_configurable() {
+    let eol_date = today() + chrono::Days::new(30);
+    let release = series("10.11", Some(eol_date));
+    assert_eq!(
+        policy(0, false).status(Some(&release), today()),
+        EolStatus::Supported {
+            eol_date: Some(eol_date)
+        }
+    );
+    assert_eq!(
+        policy(29, false).status(Some(&release), today()),
+        EolStatus::Supported {
+            eol_date: Some(eol_date)
+        }
+    );
+    assert_eq!(
+        policy(30, false).status(Some(&release), today()),
+        EolStatus::NearEol {
+            eol_date,
+            days_left: 30
+        }
+    );
+}
+
+#[test]
+fn only_fails_past_eol_with_fail_on_eol() {
+    let releases = vec![
+        series("10.5", Some(date(2025, 6, 24))),
+        series("10.11", Some(today() + chrono::Days::new(10))),
+        series("11.4", Some(date(2029, 5, 29))),
+    ];
+
+    // Point releases and series are checked against the series
+    for version in ["10.5.27", "10.5"] {
+        assert_eq!(
+            policy(90, false)
+                .check(version, &releases, today())
+                .unwrap(),
+            EolStatus::Eol {
+                eol_date: date(2025, 6, 24)
+            }
+        );
+        let error = policy(90, true)
+            .check(version, &releases, today())
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            error,
+            format!(
+                "Past end of life, with `--fail-on-eol`; upgrade to a supported series:\n  {} (EOL since 2025-06-24)",
+                version
+            )
+        );
+    }
+    // Near is only ever a warning
+    assert!(matches!(
+        policy(90, true).check("10.11.10", &releases, today()),
+        Ok(EolStatus::NearEol { days_left: 10, .. })
+    ));
+    assert!(matches!(
+        policy(90, true).check("11.4.2", &releases, today()),
+        Ok(EolStatus::Supported { .. })
+    ));
+    // Not knowing is not failing
+    assert_eq!(
+        policy(90, true)
+            .check("12.0.1", &releases, today())
+            .unwrap(),
+        EolStatus::Unknown
+    );
+}
+
+#[test]
+fn enforce_lists_every_version_past_eol() {
+    assert!(policy(90, false)
+        .enforce(vec![String::from("10.5.27")])
+        .is_ok());
+    assert!(policy(90, true).enforce(Vec::new()).is_ok());
+    let error = policy(90, true)
+        .enforce(vec![String::from("10.5.27"), String::from("10.6.20")])
+        .unwrap_err()
+        .to_string();
+    assert!(error.ends_with(":\n  10.5.27\n  10.6.20"), "{}", error);
+}
@@ -0,0 +1,259 @@
+//! Initialising data directories with `init::init`, against a stand-in `mariadb-install-db`
+//! and `mariadbd` that record how they were run
+#![cfg(unix)]
+
+use std::path::Path;
+
+use mariadb_version_manager_rs::init::{self, InitManifest, RootAuthentication};
+use mariadb_version_manager_rs::instance::Instance;
+use mariadb_version_manager_rs::uri::{self, Credentials};
+
+mod common;
+use common::scratch;
+
+/// Creates the `mysql` schema in `--datadir`, and logs its arguments
+const FAKE_INSTALL_DB: &str = r#"#!/bin/sh
+echo "$*" >> "$(dirname "$0")/install-db.log"
+for arg; do
+    case "$arg" in --datadir=*) mkdir -p "${arg#*=}/mysql" ;; esac
+done
+"#;
+
+/// Takes the SQL a bootstrapping server reads
+const FAKE_SERVER: &str = r#"#!/bin/sh
+echo "$*" >> "$(dirname "$0")/mariadbd.log"
+cat >> "$(dirname "$0")/bootstrap.sql"
+"#;
+
+fn executable(path: &Path, script: &str) {
+    use std::os::unix::fs::PermissionsExt;
+
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, script).unwrap();
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+/// An instance of a stand-in installation under `dir`, not yet initialised
+fn instance(dir: &Path) -> Instance {
+    let prefix = dir.join("bin-path");
+    executable(&prefix.join("scripts/mariadb-install-db"), FAKE_INSTALL_DB);
+    executable(&prefix.join("bin/mariadbd"), FAKE_SERVER);
+    Instance {
+        version: String::from("11.4.2"),
+        prefix,
+        data_path: dir.join("data"),
+        runtime_path: dir.join("run"),
+        logs_path: dir.join("logs"),
+        hostname: String::from("localhost"),
+        port: 3306,
+        database: String::from("app`db"),
+        locale: String::from("en_US.UTF-8"),
+        defaults_file: dir.join("my.cnf"),
+        includes: Vec::new(),
+    }
+}
+
+#[test]
+fn generates_an_account_for_the_database() {
+    let dir = scratch("init-credentials");
+    let instance = instance(&dir);
+
+    let manifest = init::init(&instance, RootAuthentication::Socket).unwrap();
+    let generated = manifest.credentials.clone().unwrap();
+    assert_eq!(generated.user, "mariadb_vm");
+    assert_eq!(generated.password.len(), 24);
+    assert!(generated
+        .password
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric()));
+
+    let bin = instance.prefix.join("bin");
+    assert_eq!(
+        std::fs::read_to_string(bin.join("mariadbd.log")).unwrap(),
+        format!(
+            "{} --bootstrap --basedir={} --datadir={}\n",
+            instance.defaults_file_arg(),
+            instance.prefix.display(),
+            instance.data_path.display()
+        )
+    );
+    let sql = std::fs::read_to_string(bin.join("bootstrap.sql")).unwrap();
+    assert!(sql.starts_with("FLUSH PRIVILEGES;\n"), "{}", sql);
+    for host in ["localhost", "127.0.0.1", "::1"] {
+        assert!(sql.contains(&format!(
+            "CREATE USER IF NOT EXISTS 'mariadb_vm'@'{}' IDENTIFIED BY '{}';\n\
+             GRANT ALL PRIVILEGES ON `app``db`.* TO 'mariadb_vm'@'{}';\n",
+            host, generated.password, host
+        )));
+    }
+
+    // Kept from the one `init`, for `uri --with-credentials`
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let manifest_file = instance.data_path.join("mariadb-vm-init.json");
+        let mode = std::fs::metadata(manifest_file)
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+    let again = init::init(&instance, RootAuthentication::Socket).unwrap();
+    assert_eq!(again.credentials, Some(generated.clone()));
+    let credentials = Credentials::of(&instance).unwrap();
+    assert_eq!(credentials.user, generated.user);
+    assert_eq!(
+        credentials.password.as_deref(),
+        Some(generated.password.as_str())
+    );
+    assert_eq!(
+        uri::render(
+            &Instance {
+                hostname: String::from("127.0.0.1"),
+                ..instance.clone()
+            },
+            "mysql".parse().unwrap(),
+            Some(&credentials)
+        ),
+        format!(
+            "mysql://mariadb_vm:{}@127.0.0.1:3306/app%60db",
+            generated.password
+        )
+    );
+}
+
+#[test]
+fn generates_no_account_on_existing_system_tables() {
+    let dir = scratch("init-existing-system-tables");
+    let instance = instance(&dir);
+    std::fs::create_dir_all(instance.data_path.join("mysql")).unwrap();
+
+    let manifest = init::init(&instance, RootAuthentication::Normal).unwrap();
+    assert_eq!(manifest.credentials, None);
+    assert!(!instance.prefix.join("scripts/install-db.log").exists());
+    assert!(!instance.prefix.join("bin/mariadbd.log").exists());
+    // Whoever set it up knows the password
+    let credentials = Credentials::of(&instance).unwrap();
+    assert_eq!(credentials.user, "root");
+    assert_eq!(credentials.password, None);
+    assert_eq!(
+        InitManifest::read(&instance.data_path)
+            .unwrap()
+            .unwrap()
+            .root_authentication,
+        RootAuthentication::Normal
+    );
+}
+
+#[test]
+fn fails_when_the_account_cannot_be_created() {
+    let dir = scratch("init-bootstrap-fails");
+    let instance = instance(&dir);
+    executable(
+        &instance.prefix.join("bin/mariadbd"),
+        "#!/bin/sh\ncat > /dev/null\nexit 1\n",
+    );
+
+    let error = init::init(&instance, RootAuthentication::Socket)
+        .unwrap_err()
+        .to_string();
+    assert!(
+        error.starts_with("Initialising the data directory failed:"),
+        "{}",
+        error
+    );
+    assert!(error.contains("--bootstrap"), "{}", error);
+    assert!(InitManifest::read(&instance.data_path).unwrap().is_none());
+}
+
+#[test]
+fn creates_the_directories_and_the_database() {
+    let dir = scratch("init-directories");
+    let instance = Instance {
+        database: String::from("my-db"),
+        locale: String::from("de_DE.ISO-8859-1"),
+        ..instance(&dir)
+    };
+
+    let manifest = init::init(&instance, RootAuthentication::Normal).unwrap();
+    for path in [
+        &instance.data_path,
+        &instance.runtime_path,
+        &instance.logs_path,
+    ] {
+        assert!(path.is_dir(), "{:?}", path);
+    }
+    assert!(instance.defaults_file.is_file());
+    assert_eq!(
+        std::fs::read_to_string(instance.prefix.join("scripts/install-db.log")).unwrap(),
+        format!(
+            "{} --basedir={} --datadir={} --auth-root-authentication-method=normal\n",
+            instance.defaults_file_arg(),
+            instance.prefix.display(),
+            instance.data_path.display()
+        )
+    );
+    // Encoded as MariaDB names it on disk, with the locale's character set
+    assert_eq!(
+        std::fs::read_to_string(instance.data_path.join("my@002ddb").join("db.opt")).unwrap(),
+        "default-character-set=latin1\ndefault-collation=latin1_swedish_ci\n"
+    );
+    assert_eq!(manifest.database, "my-db");
+    assert_eq!(manifest.data_path, instance.data_path);
+    assert_eq!(manifest.prefix, instance.prefix);
+}
+
+#[test]
+fn initialises_once_but_creates_each_database() {
+    let dir = scratch("init-idempotent");
+    let instance = instance(&dir);
+    let first = init::init(&instance, RootAuthentication::Socket).unwrap();
+
+    // Another `--database`, on the same data directory
+    let other = Instance {
+        database: String::from("other"),
+        ..instance.clone()
+    };
+    let again = init::init(&other, RootAuthentication::Normal).unwrap();
+    assert_eq!(again.initialized_at, first.initialized_at);
+    assert_eq!(again.root_authentication, RootAuthentication::Socket);
+    assert_eq!(
+        std::fs::read_to_string(instance.prefix.join("scripts/install-db.log"))
+            .unwrap()
+            .lines()
+            .count(),
+        1
+    );
+    assert!(instance.data_path.join("other").join("db.opt").is_file());
+}
+
+#[test]
+fn leaves_out_the_authentication_method_before_10_4() {
+    let dir = scratch("init-10.3");
+    let instance = Instance {
+        version: String::from("10.3.39"),
+        ..instance(&dir)
+    };
+    std::fs::rename(
+        instance.prefix.join("scripts/mariadb-install-db"),
+        instance.prefix.join("scripts/mysql_install_db"),
+    )
+    .unwrap();
+
+    init::init(&instance, RootAuthentication::Socket).unwrap();
+    let log = std::fs::read_to_string(instance.prefix.join("scripts/install-db.log")).unwrap();
+    assert!(
+        !log.contains("--auth-root-authentication-method"),
+        "{}",
+        log
+    );
+}
+
+#[test]
+fn needs_an_install_db_script() {
+    let dir = scratch("init-no-script");
+    let instance = instance(&dir);
+    std::fs::remove_file(instance.prefix.join("scripts/mariadb-install-db")).unwrap();
+
+    assert!(init::init(&instance, RootAuthentication::Socket).is_err());
+    assert!(InitManifest::read(&instance.data_path).unwrap().is_none());
+}
@@ -0,0 +1,350 @@
+//! Extracting bintars into a prefix with `install::install`, and finding them again
+
+use std::path::Path;
+
+use mariadb_version_manager_rs::{install, installed};
+
+mod common;
+use common::scratch;
+
+enum Entry<'a> {
+    File(&'a str, &'a [u8]),
+    Symlink(&'a str, &'a str),
+    HardLink(&'a str, &'a str),
+}
+
+/// Write `entries` to `<dir>/<name>`. Names are set byte for byte, as `tar::Builder` would
+/// refuse the malicious ones.
+fn archive(dir: &Path, name: &str, entries: &[Entry]) -> std::ffi::OsString {
+    let path = dir.join(name);
+    let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+        std::fs::File::create(&path).unwrap(),
+        flate2::Compression::fast(),
+    ));
+    for entry in entries {
+        let mut header = tar::Header::new_gnu();
+        let (entry_path, data): (&str, &[u8]) = match entry {
+            Entry::File(entry_path, data) => {
+                header.set_entry_type(tar::EntryType::Regular);
+                (entry_path, data)
+            }
+            Entry::Symlink(entry_path, target) | Entry::HardLink(entry_path, target) => {
+                header.set_entry_type(match entry {
+                    Entry::Symlink(..) => tar::EntryType::Symlink,
+                    _ => tar::EntryType::Link,
+                });
+                header.as_old_mut().linkname[..target.len()].copy_from_slice(target.as_bytes());
+                (entry_path, b"")
+            }
+        };
+        header.as_old_mut().name[..entry_path.len()].copy_from_slice(entry_path.as_bytes());
+        header.set_size(data.len() as u64);
+        header.set_mode(0o755);
+        header.set_cksum();
+        builder.append(&header, data).unwrap();
+    }
+    builder.into_inner().unwrap().finish().unwrap();
+    path.into_os_string()
+}
+
+fn install(dir: &Path, archive: &std::ffi::OsString) -> Result<install::InstallManifest, String> {
+    install_into(dir, "prefix", archive)
+}
+
+fn install_into(
+    dir: &Path,
+    prefix: &str,
+    archive: &std::ffi::OsString,
+) -> Result<install::InstallManifest, String> {
+    install::install(
+        archive,
+        &dir.join(prefix).into_os_string(),
+        &dir.join("root").into_os_string(),
+    )
+    .map_err(|e| e.to_string())
+}
+
+#[test]
+fn strips_the_top_level_directory() {
+    let dir = scratch("install-strip");
+    let archive = archive(
+        &dir,
+        "mariadb-11.4.2-linux-systemd-x86_64.tar.gz",
+        &[
+            Entry::File(
+                "mariadb-11.4.2-linux-systemd-x86_64/bin/mariadbd",
+                b"server",
+            ),
+            Entry::Symlink("mariadb-11.4.2-linux-systemd-x86_64/bin/mysqld", "mariadbd"),
+        ],
+    );
+    let manifest = install(&dir, &archive).unwrap();
+    assert_eq!(manifest.version, "11.4.2");
+    let prefix = dir.join("prefix");
+    assert_eq!(
+        std::fs::read(prefix.join("bin/mariadbd")).unwrap(),
+        b"server"
+    );
+    assert_eq!(std::fs::read(prefix.join("bin/mysqld")).unwrap(), b"server");
+    assert!(!prefix.join("mariadb-11.4.2-linux-systemd-x86_64").exists());
+}
+
+#[test]
+fn rejects_parent_dir_entries() {
+    let dir = scratch("install-parent-dir");
+    let archive = archive(
+        &dir,
+        "mariadb-11.4.2-linux-x86_64.tar.gz",
+        &[Entry::File(
+            "mariadb-11.4.2-linux-x86_64/../../escaped",
+            b"x",
+        )],
+    );
+    assert!(install(&dir, &archive)
+        .unwrap_err()
+        .contains("Refusing to extract"));
+    assert!(!dir.join("escaped").exists());
+}
+
+#[test]
+fn rejects_writing_through_a_symlink_out_of_the_prefix() {
+    let dir = scratch("install-symlink-escape");
+    let outside = dir.join("outside");
+    std::fs::create_dir_all(&outside).unwrap();
+    let archive = archive(
+        &dir,
+        "mariadb-11.4.2-linux-x86_64.tar.gz",
+        &[
+            Entry::Symlink("mariadb-11.4.2-linux-x86_64/lib", outside.to_str().unwrap()),
+            Entry::File("mariadb-11.4.2-linux-x86_64/lib/passwd", b"x"),
+        ],
+    );
+    assert!(install(&dir, &archive)
+        .unwrap_err()
+        .contains("Refusing to extract"));
+    assert!(!outside.join("passwd").exists());
+}
+
+#[test]
+fn rejects_relative_symlinks_out_of_the_prefix() {
+    let dir = scratch("install-symlink-relative");
+    let archive = archive(
+        &dir,
+        "mariadb-11.4.2-linux-x86_64.tar.gz",
+        &[Entry::Symlink(
+            "mariadb-11.4.2-linux-x86_64/lib/plugin",
+            "../../outside",
+        )],
+    );
+    assert!(install(&dir, &archive)
+        .unwrap_err()
+        .contains("Refusing to extract"));
+}
+
+#[test]
+fn links_hard_links_within_the_prefix() {
+    let dir = scratch("install-hard-link");
+    let archive = archive(
+        &dir,
+        "mariadb-11.4.2-linux-x86_64.tar.gz",
+        &[
+            Entry::File("mariadb-11.4.2-linux-x86_64/bin/mariadb", b"client"),
+            Entry::HardLink(
+                "mariadb-11.4.2-linux-x86_64/bin/mysql",
+                "mariadb-11.4.2-linux-x86_64/bin/mariadb",
+            ),
+        ],
+    );
+    install(&dir, &archive).unwrap();
+    assert_eq!(
+        std::fs::read(dir.join("prefix/bin/mysql")).unwrap(),
+        b"client"
+    );
+}
+
+#[test]
+fn rejects_hard_links_out_of_the_prefix() {
+    let dir = scratch("install-hard-link-escape");
+    let archive = archive(
+        &dir,
+        "mariadb-11.4.2-linux-x86_64.tar.gz",
+        &[Entry::HardLink(
+            "mariadb-11.4.2-linux-x86_64/bin/mysql",
+            "mariadb-11.4.2-linux-x86_64/../../../etc/passwd",
+        )],
+    );
+    assert!(install(&dir, &archive).is_err());
+    assert!(!dir.join("prefix/bin/mysql").exists());
+}
+
+#[test]
+fn reinstalling_into_a_prefix_replaces_the_previous_release() {
+    let dir = scratch("install-reinstall");
+    let old = archive(
+        &dir,
+        "mariadb-11.4.1-linux-x86_64.tar.gz",
+        &[Entry::File(
+            "mariadb-11.4.1-linux-x86_64/lib/plugin/old.so",
+            b"old",
+        )],
+    );
+    let new = archive(
+        &dir,
+        "mariadb-11.4.2-linux-x86_64.tar.gz",
+        &[Entry::File(
+            "mariadb-11.4.2-linux-x86_64/lib/plugin/new.so",
+            b"new",
+        )],
+    );
+    install(&dir, &old).unwrap();
+    install(&dir, &new).unwrap();
+    assert!(!dir.join("prefix/lib/plugin/old.so").exists());
+    assert!(dir.join("prefix/lib/plugin/new.so").exists());
+    let installed = install::read_installed(&dir.join("root").into_os_string()).unwrap();
+    let versions: Vec<&str> = installed
+        .values()
+        .map(|manifest| manifest.version.as_str())
+        .collect();
+    assert_eq!(versions, vec!["11.4.2"]);
+}
+
+#[test]
+fn strips_a_leading_cur_dir() {
+    let dir = scratch("install-cur-dir");
+    let archive = archive(
+        &dir,
+        "mariadb-11.4.2-linux-x86_64.tar.gz",
+        &[
+            Entry::File("./mariadb-11.4.2-linux-x86_64/bin/mariadb", b"client"),
+            Entry::HardLink(
+                "./mariadb-11.4.2-linux-x86_64/bin/mysql",
+                "./mariadb-11.4.2-linux-x86_64/bin/mariadb",
+            ),
+        ],
+    );
+    let manifest = install(&dir, &archive).unwrap();
+    assert_eq!(manifest.version, "11.4.2");
+    assert_eq!(
+        std::fs::read(dir.join("prefix/bin/mysql")).unwrap(),
+        b"client"
+    );
+}
+
+#[test]
+fn a_bad_archive_leaves_the_previous_release() {
+    let dir = scratch("install-bad-archive");
+    let good = archive(
+        &dir,
+        "mariadb-11.4.2-linux-x86_64.tar.gz",
+        &[Entry::File(
+            "mariadb-11.4.2-linux-x86_64/bin/mariadbd",
+            b"server",
+        )],
+    );
+    install(&dir, &good).unwrap();
+
+    let zip = dir.join("mariadb-11.4.3-winx64.zip");
+    std::fs::write(&zip, b"PK\x03\x04").unwrap();
+    let corrupt = dir.join("mariadb-11.4.3-linux-x86_64.tar.gz");
+    std::fs::write(&corrupt, b"not gzip").unwrap();
+    let empty = archive(&dir, "mariadb-11.4.4-linux-x86_64.tar.gz", &[]);
+    let escaping = archive(
+        &dir,
+        "mariadb-11.4.5-linux-x86_64.tar.gz",
+        &[
+            Entry::File("mariadb-11.4.5-linux-x86_64/bin/mariadbd", b"new"),
+            Entry::File("mariadb-11.4.5-linux-x86_64/../../escaped", b"x"),
+        ],
+    );
+    for bad in [
+        zip.into_os_string(),
+        corrupt.into_os_string(),
+        empty,
+        escaping,
+    ] {
+        assert!(install(&dir, &bad).is_err(), "{:?}", bad);
+        assert_eq!(
+            std::fs::read(dir.join("prefix/bin/mariadbd")).unwrap(),
+            b"server"
+        );
+    }
+    assert!(!dir.join(".prefix.new").exists());
+    let installed = install::read_installed(&dir.join("root").into_os_string()).unwrap();
+    assert_eq!(installed.values().next().unwrap().version, "11.4.2");
+}
+
+#[test]
+fn refuses_to_replace_a_prefix_it_did_not_install() {
+    let dir = scratch("install-unmanaged");
+    std::fs::create_dir_all(dir.join("prefix/bin")).unwrap();
+    std::fs::write(dir.join("prefix/bin/other"), b"other").unwrap();
+    let archive = archive(
+        &dir,
+        "mariadb-11.4.2-linux-x86_64.tar.gz",
+        &[Entry::File(
+            "mariadb-11.4.2-linux-x86_64/bin/mariadbd",
+            b"server",
+        )],
+    );
+    assert!(install(&dir, &archive)
+        .unwrap_err()
+        .contains("was not installed by `install`"));
+    assert!(dir.join("prefix/bin/other").exists());
+    assert!(!dir.join("prefix/bin/mariadbd").exists());
+
+    // An empty directory is fair game
+    std::fs::remove_dir_all(dir.join("prefix/bin")).unwrap();
+    install(&dir, &archive).unwrap();
+}
+
+#[test]
+fn records_one_version_in_two_prefixes() {
+    let dir = scratch("install-two-prefixes");
+    let archive = archive(
+        &dir,
+        "mariadb-11.4.2-linux-x86_64.tar.gz",
+        &[Entry::File(
+            "mariadb-11.4.2-linux-x86_64/bin/mariadbd",
+            b"server",
+        )],
+    );
+    install_into(&dir, "prefix-a", &archive).unwrap();
+    install_into(&dir, "prefix-b", &archive).unwrap();
+    let installed = install::read_installed(&dir.join("root").into_os_string()).unwrap();
+    let prefixes: Vec<&Path> = installed
+        .values()
+        .map(|manifest| manifest.prefix.as_path())
+        .collect();
+    assert_eq!(prefixes, vec![dir.join("prefix-a"), dir.join("prefix-b")]);
+}
+
+#[test]
+fn finds_the_installation_of_a_version() {
+    let dir = scratch("install-find-version");
+    let archive = archive(
+        &dir,
+        "mariadb-11.4.2-linux-x86_64.tar.gz",
+        &[Entry::File(
+            "mariadb-11.4.2-linux-x86_64/bin/mariadbd",
+            b"server",
+        )],
+    );
+    install_into(&dir, "prefix-a", &archive).unwrap();
+    install_into(&dir, "prefix-b", &archive).unwrap();
+    let root = dir.join("root").into_os_string();
+    let find = |prefix: &str| {
+        installed::find_version(&root, "11.4.2", &dir.join(prefix)).map(|found| found.prefix)
+    };
+    // The one in `--bin-path`, else the last installed
+    assert_eq!(find("prefix-a").unwrap(), dir.join("prefix-a"));
+    assert_eq!(find("elsewhere").unwrap(), dir.join("prefix-b"));
+
+    let error = installed::find_version(&root, "10.11.8", &dir.join("prefix-a"))
+        .unwrap_err()
+        .to_string();
+    assert!(
+        error.starts_with("MariaDB 10.11.8 is not installed"),
+        "{}",
+        error
+    );
+}
@@ -0,0 +1,120 @@
+//! Registering named instances with `instance::named_instance`: where they live, and which
+//! port each gets
+
+use mariadb_version_manager_rs::instance::{self, NamedInstance};
+
+mod common;
+use common::scratch;
+
+const HOSTNAME: &str = "127.0.0.1";
+
+/// A port with the next few free too, as far as can be told
+fn free_ports() -> u16 {
+    loop {
+        let port = std::net::TcpListener::bind((HOSTNAME, 0))
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        if port < u16::MAX - 8
+            && (port..port + 8).all(|port| std::net::TcpListener::bind((HOSTNAME, port)).is_ok())
+        {
+            return port;
+        }
+    }
+}
+
+fn register(root: &std::ffi::OsString, version: &str, name: &str, port: u16) -> NamedInstance {
+    instance::named_instance(root, version, name, HOSTNAME, port).unwrap()
+}
+
+#[test]
+fn lives_under_the_version_next_to_the_default_instance() {
+    let root = scratch("instance-dir").into_os_string();
+    let named = register(&root, "11.4.2", "replica", free_ports());
+    let dir = std::path::Path::new(&root)
+        .join("mariadb-version-manager-rs")
+        .join("11.4.2")
+        .join("instances")
+        .join("replica");
+    assert_eq!(
+        dir,
+        instance::named_instance_dir(&root, "11.4.2", "replica")
+    );
+    assert_eq!(
+        instance::defaults_file_path(&root, "11.4.2").parent(),
+        dir.parent().and_then(|instances| instances.parent())
+    );
+    assert_eq!(named.data_path, dir.join("data"));
+    assert_eq!(named.runtime_path, dir.join("run"));
+    assert_eq!(named.logs_path, dir.join("logs"));
+    assert_eq!(named.defaults_file, dir.join("my.cnf"));
+}
+
+#[test]
+fn takes_the_first_free_port_after_the_default_instance() {
+    let root = scratch("instance-free-port").into_os_string();
+    let port = free_ports();
+    // Someone else's server
+    let _listener = std::net::TcpListener::bind((HOSTNAME, port + 1)).unwrap();
+
+    assert_eq!(register(&root, "11.4.2", "a", port).port, port + 2);
+}
+
+#[test]
+fn skips_the_ports_of_registered_instances() {
+    let root = scratch("instance-registered-ports").into_os_string();
+    let port = free_ports();
+
+    assert_eq!(register(&root, "11.4.2", "a", port).port, port + 1);
+    // Registered, though not running, and whatever its version
+    assert_eq!(register(&root, "10.11.8", "b", port).port, port + 2);
+    assert_eq!(register(&root, "11.4.2", "c", port).port, port + 3);
+    // Already registered
+    assert_eq!(register(&root, "11.4.2", "a", port).port, port + 1);
+}
+
+#[test]
+fn round_trips_the_registry() {
+    let root = scratch("instance-registry").into_os_string();
+    assert!(instance::read_named_instances(&root).unwrap().is_empty());
+    let port = free_ports();
+    let a = register(&root, "11.4.2", "a", port);
+    let b = register(&root, "10.11.8", "b", port);
+
+    let registry = instance::read_named_instances(&root).unwrap();
+    assert_eq!(
+        registry.keys().collect::<Vec<_>>(),
+        vec!["10.11.8/b", "11.4.2/a"]
+    );
+    for (read, registered) in [(&registry["11.4.2/a"], &a), (&registry["10.11.8/b"], &b)] {
+        assert_eq!(read.name, registered.name);
+        assert_eq!(read.version, registered.version);
+        assert_eq!(read.port, registered.port);
+        assert_eq!(read.data_path, registered.data_path);
+        assert_eq!(read.defaults_file, registered.defaults_file);
+        assert_eq!(read.created_at, registered.created_at);
+    }
+
+    // Registries written before the version was called that
+    let registry_file = std::path::Path::new(&root).join("instances.json");
+    let written = std::fs::read_to_string(&registry_file).unwrap();
+    std::fs::write(
+        &registry_file,
+        written.replace("\"version\":", "\"app_version\":"),
+    )
+    .unwrap();
+    assert_eq!(
+        instance::read_named_instances(&root).unwrap()["11.4.2/a"].version,
+        "11.4.2"
+    );
+}
+
+#[test]
+fn rejects_names_that_are_not_one_path_component() {
+    let root = scratch("instance-names").into_os_string();
+    for name in ["", "../a", "a/b", "a b"] {
+        assert!(instance::named_instance(&root, "11.4.2", name, HOSTNAME, 3306).is_err());
+    }
+    assert!(!std::path::Path::new(&root).join("instances.json").exists());
+}
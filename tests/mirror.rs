@@ -0,0 +1,347 @@
+//! Ranking mirrors with `mirror::rank_mirrors`, failing over between them in
+//! `mariadb_vm::download_from_plan`, and listing, probing, and pinning them for `mirrors`
+
+use mariadb_version_manager_rs::api_cache::ApiCache;
+use mariadb_version_manager_rs::artifact::{ArtifactSelector, PackageType};
+use mariadb_version_manager_rs::mariadb_config::MariaDbConfig;
+use mariadb_version_manager_rs::mariadb_vm::{self, DownloadPlan, Endpoints};
+use mariadb_version_manager_rs::mirror::{self, MirrorOptions, MirrorStrategy};
+use sha2::Digest;
+
+mod common;
+use common::{scratch, MockServer, Response};
+
+const MIRROR_LIST: &str = r#"{"mirror_list": {
+    "US": [{"mirror_id": "us1", "mirror_name": "US 1"}, {"mirror_id": "us2", "mirror_name": "US 2"}],
+    "FI": [{"mirror_id": "fi1", "mirror_name": "FI 1"}],
+    "DE": [{"mirror_id": "de1", "mirror_name": "DE 1"}, {"mirror_id": "de2", "mirror_name": "DE 2"}]
+}}"#;
+
+/// Where nothing listens; only the `fastest` strategy would ask
+fn unreachable_url(mirror_id: &str) -> reqwest::Url {
+    reqwest::Url::parse(&format!("http://127.0.0.1:9/{}", mirror_id)).unwrap()
+}
+
+async fn rank(options: &MirrorOptions) -> Vec<String> {
+    mirror::rank_mirrors(
+        &reqwest::Client::new(),
+        &serde_json::from_str(MIRROR_LIST).unwrap(),
+        options,
+        &|mirror_id| Ok(unreachable_url(mirror_id)),
+    )
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn ordered_follows_the_preference_then_the_list() {
+    let ranked = rank(&MirrorOptions {
+        strategy: MirrorStrategy::Ordered,
+        preference: vec![String::from("us2"), String::from("fi")],
+        ..MirrorOptions::default()
+    })
+    .await;
+    // Then the rest as listed, sorted by country; at most four
+    assert_eq!(ranked, vec!["us2", "fi1", "de1", "de2"]);
+}
+
+#[tokio::test]
+async fn a_pinned_mirror_comes_first() {
+    let ranked = rank(&MirrorOptions {
+        mirror: Some(String::from("us1")),
+        strategy: MirrorStrategy::Ordered,
+        preference: vec![String::from("de2")],
+    })
+    .await;
+    assert_eq!(ranked, vec!["us1", "de2", "de1", "fi1"]);
+
+    for _ in 0..8 {
+        let ranked = rank(&MirrorOptions {
+            mirror: Some(String::from("fi1")),
+            ..MirrorOptions::default()
+        })
+        .await;
+        assert_eq!(ranked.len(), mirror::MAX_MIRROR_ATTEMPTS);
+        assert_eq!(ranked[0], "fi1");
+        assert!(!ranked[1..].contains(&String::from("fi1")));
+    }
+}
+
+#[tokio::test]
+async fn an_empty_mirror_list_needs_a_pinned_mirror() {
+    let empty: mariadb_vm::ListAvailableMirrorsRoot =
+        serde_json::from_str(r#"{"mirror_list": {}}"#).unwrap();
+    let mirror_url = |mirror_id: &str| Ok(unreachable_url(mirror_id));
+    let client = reqwest::Client::new();
+    assert!(
+        mirror::rank_mirrors(&client, &empty, &MirrorOptions::default(), &mirror_url)
+            .await
+            .is_err()
+    );
+    let pinned = MirrorOptions {
+        mirror: Some(String::from("us1")),
+        ..MirrorOptions::default()
+    };
+    assert_eq!(
+        mirror::rank_mirrors(&client, &empty, &pinned, &mirror_url)
+            .await
+            .unwrap(),
+        vec!["us1"]
+    );
+}
+
+const BODY: &[u8] = b"mariadb-11.4.2-linux-systemd-x86_64.tar.gz";
+
+/// The REST API, its mirror list, and the file, which the archive serves (without
+/// `?mirror=`) and so do the mirrors in `serving`. `?mirror=de1` is a 503, and any other
+/// mirror a 404.
+fn api_server(serving: &'static [&'static str]) -> MockServer {
+    let signature_url = unreachable_url("mariadb-11.4.2-linux-systemd-x86_64.tar.gz.asc");
+    let releases = serde_json::json!({"releases": {"11.4.2": {
+        "release_id": "11.4.2",
+        "release_name": "MariaDB Server 11.4.2",
+        "date_of_release": "2024-05-29",
+        "release_notes_url": "",
+        "change_log": "",
+        "files": [{
+            "file_id": 1234,
+            "file_name": "mariadb-11.4.2-linux-systemd-x86_64.tar.gz",
+            "package_type": "gzipped tar file",
+            "os": "Linux",
+            "cpu": "x86_64",
+            "checksum": {"sha256sum": hex::encode(sha2::Sha256::digest(BODY))},
+            "signature": null,
+            "checksum_url": "",
+            "signature_url": signature_url.to_string(),
+            "file_download_url": ""
+        }]
+    }}})
+    .to_string();
+    MockServer::start(move |request| match request.path.as_str() {
+        "/11.4/" => Response::new(200, releases.as_bytes()),
+        "/mirrors" => Response::new(200, MIRROR_LIST.as_bytes()),
+        "/11.4.2/mariadb-11.4.2-linux-systemd-x86_64.tar.gz" => Response::new(200, BODY),
+        path => match path.strip_prefix("/11.4.2/1234?mirror=") {
+            Some(mirror) if serving.contains(&mirror) => Response::new(200, BODY),
+            Some("de1") => Response::new(503, b""),
+            _ => Response::new(404, b""),
+        },
+    })
+}
+
+/// Planned against `server`, trying the mirrors in `preference` first
+async fn plan(server: &MockServer, dir: &std::path::Path, preference: &[&str]) -> DownloadPlan {
+    mariadb_vm::download_plan(
+        "11.4.2",
+        &ArtifactSelector {
+            os: String::from("Linux"),
+            cpu: String::from("x86_64"),
+            package_type: PackageType::Bintar,
+            variants: Vec::new(),
+        },
+        &MirrorOptions {
+            mirror: None,
+            strategy: MirrorStrategy::Ordered,
+            preference: preference.iter().map(|id| id.to_string()).collect(),
+        },
+        &ApiCache::new(
+            &dir.join("vm").into_os_string(),
+            std::time::Duration::from_secs(60),
+            false,
+        ),
+        &Endpoints::new(
+            Some(server.url.clone()),
+            None,
+            Some(format!("{}/mirrors", server.url)),
+        ),
+    )
+    .await
+    .unwrap()
+}
+
+async fn download(
+    plan: &DownloadPlan,
+    dir: &std::path::Path,
+) -> Result<std::path::PathBuf, String> {
+    mariadb_vm::download_from_plan(
+        plan,
+        &dir.as_os_str().to_os_string(),
+        false,
+        &Default::default(),
+    )
+    .await
+    .map(|downloaded| downloaded.unwrap().into())
+    .map_err(|e| e.to_string())
+}
+
+/// The mirrors the file was asked of, in order; `archive` for the REST API's own URL
+fn mirrors_asked(server: &MockServer) -> Vec<String> {
+    server
+        .requests()
+        .iter()
+        .filter_map(|request| request.path.strip_prefix("/11.4.2/"))
+        .map(|file| {
+            file.split_once("?mirror=")
+                .map_or("archive", |(_, mirror)| mirror)
+                .to_string()
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn plans_the_archive_after_the_mirrors() {
+    let dir = scratch("mirror-plan");
+    let server = api_server(&[]);
+    let plan = plan(&server, &dir, &["fi1"]).await;
+    assert_eq!(plan.mirror_id.as_deref(), Some("fi1"));
+    assert_eq!(plan.url, format!("{}/11.4.2/1234?mirror=fi1", server.url));
+    let fallbacks: Vec<Option<&str>> = plan
+        .fallback_mirrors
+        .iter()
+        .map(|mirror| mirror.mirror_id.as_deref())
+        .collect();
+    assert_eq!(fallbacks, vec![Some("de1"), Some("de2"), Some("us1"), None]);
+    assert_eq!(
+        plan.fallback_mirrors.last().unwrap().url,
+        format!(
+            "{}/11.4.2/mariadb-11.4.2-linux-systemd-x86_64.tar.gz",
+            server.url
+        )
+    );
+}
+
+#[tokio::test]
+async fn fails_over_to_the_next_mirror() {
+    let dir = scratch("mirror-failover");
+    let server = api_server(&["fi1"]);
+    let plan = plan(&server, &dir, &["us1", "de1", "fi1"]).await;
+    let downloaded = download(&plan, &dir).await.unwrap();
+    assert_eq!(std::fs::read(downloaded).unwrap(), BODY);
+    assert_eq!(mirrors_asked(&server), vec!["us1", "de1", "fi1"]);
+}
+
+#[tokio::test]
+async fn fails_over_to_the_archive_after_every_mirror() {
+    let dir = scratch("mirror-failover-archive");
+    let server = api_server(&[]);
+    let plan = plan(&server, &dir, &["us1", "de1"]).await;
+    let downloaded = download(&plan, &dir).await.unwrap();
+    assert_eq!(std::fs::read(downloaded).unwrap(), BODY);
+    assert_eq!(
+        mirrors_asked(&server),
+        vec!["us1", "de1", "de2", "fi1", "archive"]
+    );
+}
+
+#[tokio::test]
+async fn fails_when_nothing_serves_the_file() {
+    let dir = scratch("mirror-failover-none");
+    let server = api_server(&[]);
+    let mut plan = plan(&server, &dir, &[]).await;
+    plan.fallback_mirrors.pop();
+    let error = download(&plan, &dir).await.unwrap_err();
+    assert!(error.contains("every mirror"), "{}", error);
+    assert_eq!(mirrors_asked(&server), vec!["de1", "de2", "fi1", "us1"]);
+}
+
+#[tokio::test]
+async fn fastest_ranks_by_latency() {
+    let slow = MockServer::start(|_| {
+        std::thread::sleep(std::time::Duration::from_millis(400));
+        Response::new(200, b"")
+    });
+    let fast = MockServer::start(|_| Response::new(200, b""));
+    let mirror_list: mariadb_vm::ListAvailableMirrorsRoot = serde_json::from_str(
+        r#"{"mirror_list": {"DE": [
+            {"mirror_id": "gone", "mirror_name": "Gone"},
+            {"mirror_id": "slow", "mirror_name": "Slow"},
+            {"mirror_id": "fast", "mirror_name": "Fast"}
+        ]}}"#,
+    )
+    .unwrap();
+    let mirror_url = |mirror_id: &str| {
+        Ok(match mirror_id {
+            "slow" => reqwest::Url::parse(&slow.url).unwrap(),
+            "fast" => reqwest::Url::parse(&fast.url).unwrap(),
+            _ => unreachable_url(mirror_id),
+        })
+    };
+    let client = reqwest::Client::new();
+
+    let ids: Vec<String> = ["gone", "slow", "fast"].map(String::from).to_vec();
+    let latencies = mirror::probe_mirrors(&client, &ids, &mirror_url)
+        .await
+        .unwrap();
+    assert_eq!(latencies[0].0, "fast");
+    assert_eq!(latencies[1].0, "slow");
+    assert!(latencies[1].1.unwrap() >= std::time::Duration::from_millis(400));
+    assert_eq!(latencies[2], (String::from("gone"), None));
+    assert_eq!(slow.requests().len(), 1);
+
+    let options = MirrorOptions {
+        strategy: MirrorStrategy::Fastest,
+        ..MirrorOptions::default()
+    };
+    assert_eq!(
+        mirror::rank_mirrors(&client, &mirror_list, &options, &mirror_url)
+            .await
+            .unwrap(),
+        vec!["fast", "slow", "gone"]
+    );
+}
+
+#[test]
+fn lists_mirrors_by_region() {
+    let mut mirror_list: mariadb_vm::ListAvailableMirrorsRoot =
+        serde_json::from_str(MIRROR_LIST).unwrap();
+    mirror_list
+        .mirror_list
+        .insert(String::from("XX"), Vec::new());
+    let regions: Vec<(&str, Vec<&str>)> = mirror::regions(&mirror_list)
+        .into_iter()
+        .map(|(region, mirrors)| {
+            (
+                region,
+                mirrors
+                    .iter()
+                    .map(|mirror| mirror.mirror_id.as_str())
+                    .collect(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        regions,
+        vec![
+            ("DE", vec!["de1", "de2"]),
+            ("FI", vec!["fi1"]),
+            ("US", vec!["us1", "us2"]),
+        ]
+    );
+}
+
+#[test]
+fn a_pinned_mirror_persists_in_the_config() {
+    let dir = scratch("mirror-pin");
+    let vms_config = dir.join("vms.json").into_os_string();
+    std::fs::write(&vms_config, r#"{"root": "/srv/mariadb"}"#).unwrap();
+    assert_eq!(MariaDbConfig::read(&vms_config).unwrap().mirror_id, None);
+
+    let mut config = MariaDbConfig::read(&vms_config).unwrap();
+    config.mirror_id = Some(String::from("fi1"));
+    config.write(&vms_config).unwrap();
+    assert_eq!(
+        MariaDbConfig::read(&vms_config)
+            .unwrap()
+            .mirror_id
+            .as_deref(),
+        Some("fi1")
+    );
+
+    config.mirror_id = None;
+    config.write(&vms_config).unwrap();
+    let document: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(&vms_config).unwrap()).unwrap();
+    // The rest of the file belongs to `version_manager_rs`
+    assert_eq!(document["root"], "/srv/mariadb");
+    assert_eq!(document["mariadb"].get("mirror_id"), None);
+}
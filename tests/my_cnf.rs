@@ -0,0 +1,93 @@
+//! Snapshots of the managed `my.cnf`, and writing it with `my_cnf::write`. After an intended
+//! change, rerun with `UPDATE_SNAPSHOTS=1` and review the diff of `tests/snapshots`.
+
+use std::path::PathBuf;
+
+use mariadb_version_manager_rs::instance::Instance;
+use mariadb_version_manager_rs::my_cnf;
+
+mod common;
+use common::{assert_snapshot, scratch};
+
+fn instance(version: &str) -> Instance {
+    let root = PathBuf::from("/srv/mariadb-version-manager-rs").join(version);
+    Instance {
+        version: version.to_string(),
+        prefix: PathBuf::from("/opt/mariadb").join(version),
+        data_path: root.join("my data"),
+        runtime_path: PathBuf::from("/run/mariadb").join(version),
+        logs_path: root.join("logs"),
+        hostname: String::from("127.0.0.1"),
+        port: 3307,
+        database: String::from("database"),
+        locale: String::from("en_US.UTF-8"),
+        defaults_file: root.join("my.cnf"),
+        includes: Vec::new(),
+    }
+}
+
+/// A file, which need not exist to be rendered, and a directory, which has to
+#[cfg(unix)]
+#[test]
+fn my_cnf_with_includes() {
+    let instance = Instance {
+        includes: vec![
+            PathBuf::from("/etc/mariadb-version-manager-rs/replica.cnf"),
+            PathBuf::from("/tmp"),
+        ],
+        ..instance("11.4.2")
+    };
+    assert_snapshot("my.cnf", &my_cnf::render(&instance));
+}
+
+#[test]
+fn my_cnf_before_10_4() {
+    let instance = Instance {
+        locale: String::from("de_DE.ISO-8859-1"),
+        ..instance("10.3.39")
+    };
+    assert_snapshot("my.cnf-10.3", &my_cnf::render(&instance));
+}
+
+#[test]
+fn writes_only_when_changed() {
+    let dir = scratch("my-cnf-write");
+    let include = dir.join("replica.cnf");
+    std::fs::write(&include, "[mariadbd]\nserver-id = 2\n").unwrap();
+    let instance = Instance {
+        defaults_file: dir.join("11.4.2").join("my.cnf"),
+        includes: vec![include],
+        ..instance("11.4.2")
+    };
+
+    assert!(my_cnf::write(&instance).unwrap());
+    assert_eq!(
+        std::fs::read_to_string(&instance.defaults_file).unwrap(),
+        my_cnf::render(&instance)
+    );
+    assert!(!my_cnf::write(&instance).unwrap());
+    let moved = Instance {
+        port: 3308,
+        ..instance.clone()
+    };
+    assert!(my_cnf::write(&moved).unwrap());
+}
+
+#[test]
+fn refuses_includes_that_are_missing_or_relative() {
+    let dir = scratch("my-cnf-includes");
+    for include in [dir.join("missing.cnf"), PathBuf::from("replica.cnf")] {
+        let instance = Instance {
+            defaults_file: dir.join("my.cnf"),
+            includes: vec![include.clone()],
+            ..instance("11.4.2")
+        };
+        let error = my_cnf::write(&instance).unwrap_err().to_string();
+        assert!(
+            error.starts_with(&format!("No option file {:?} to include", include)),
+            "{}",
+            error
+        );
+        assert!(!instance.defaults_file.exists());
+    }
+}
@@ -0,0 +1,66 @@
+//! Snapshots of what `service install open-rc` writes. After an intended change, rerun with
+//! `UPDATE_SNAPSHOTS=1` and review the diff of `tests/snapshots`.
+
+use std::path::PathBuf;
+
+use mariadb_version_manager_rs::init::RootAuthentication;
+use mariadb_version_manager_rs::instance::Instance;
+use mariadb_version_manager_rs::openrc;
+
+mod common;
+use common::assert_snapshot;
+
+fn instance(version: &str) -> Instance {
+    let root = PathBuf::from("/srv/mariadb-version-manager-rs").join(version);
+    Instance {
+        version: version.to_string(),
+        prefix: PathBuf::from("/opt/mariadb").join(version),
+        data_path: root.join("data"),
+        runtime_path: PathBuf::from("/run/mariadb").join(version),
+        logs_path: root.join("logs"),
+        hostname: String::from("localhost"),
+        port: 3306,
+        database: String::from("database"),
+        locale: String::from("en_US.UTF-8"),
+        defaults_file: root.join("my.cnf"),
+        includes: Vec::new(),
+    }
+}
+
+fn options() -> openrc::ScriptOptions {
+    openrc::ScriptOptions {
+        user: String::from("mysql"),
+        group: String::from("mysql"),
+        root_authentication: RootAuthentication::Socket,
+    }
+}
+
+#[test]
+fn init_script() {
+    assert_snapshot("openrc-init.d", &openrc::render_init_script());
+}
+
+#[test]
+fn conf_d() {
+    assert_snapshot(
+        "openrc-conf.d",
+        &openrc::render_conf_d(&instance("10.11.8"), &options()),
+    );
+}
+
+#[test]
+fn conf_d_before_10_4() {
+    // `mysql_install_db` of 10.3 does not know `--auth-root-authentication-method`
+    assert_snapshot(
+        "openrc-conf.d-10.3",
+        &openrc::render_conf_d(&instance("10.3.39"), &options()),
+    );
+}
+
+#[test]
+fn conf_d_quotes_paths() {
+    let mut instance = instance("11.4.2");
+    instance.data_path = PathBuf::from("/srv/it's data");
+    let conf_d = openrc::render_conf_d(&instance, &options());
+    assert!(conf_d.contains("\nMARIADB_DATADIR='/srv/it'\\''s data'\n"));
+}
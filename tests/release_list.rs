@@ -0,0 +1,96 @@
+//! `ls-remote --format json|csv` output, as other programs read it
+
+use mariadb_version_manager_rs::release_list::{self, ListFormat, RemoteRelease};
+
+fn rows() -> Vec<RemoteRelease> {
+    vec![
+        RemoteRelease {
+            version: String::from("11.4"),
+            name: String::from("MariaDB Server 11.4"),
+            status: String::from("Stable"),
+            support: String::from("Long Term Support"),
+            release_date: None,
+            eol_date: chrono::NaiveDate::from_ymd_opt(2029, 5, 29),
+            eol: Some(false),
+            binary: None,
+        },
+        RemoteRelease {
+            version: String::from("11.4.2"),
+            name: String::from("MariaDB Server 11.4.2, \"the second\""),
+            status: String::from("Stable"),
+            support: String::from("Long Term Support"),
+            release_date: Some(String::from("2024-05-16")),
+            eol_date: chrono::NaiveDate::from_ymd_opt(2029, 5, 29),
+            eol: Some(false),
+            binary: Some(true),
+        },
+    ]
+}
+
+/// RFC 4180 records; fields in quotes may hold commas, line breaks, and doubled quotes
+fn parse_csv(csv: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = csv.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            (true, '"') => quoted = false,
+            (true, c) => field.push(c),
+            (false, '"') => quoted = true,
+            (false, ',') => record.push(std::mem::take(&mut field)),
+            (false, '\n') => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            (false, c) => field.push(c),
+        }
+    }
+    assert!(field.is_empty() && record.is_empty(), "no final line break");
+    records
+}
+
+#[test]
+fn json_is_an_array_of_releases() {
+    let json = release_list::render(&rows(), ListFormat::Json).unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+    let releases = parsed.as_array().unwrap();
+    assert_eq!(releases.len(), 2);
+    assert_eq!(releases[0]["version"], "11.4");
+    assert_eq!(releases[0]["release_date"], serde_json::Value::Null);
+    assert_eq!(releases[0]["eol_date"], "2029-05-29");
+    assert_eq!(releases[1]["name"], "MariaDB Server 11.4.2, \"the second\"");
+    assert_eq!(releases[1]["binary"], true);
+}
+
+#[test]
+fn csv_has_a_header_and_one_record_per_release() {
+    let csv = release_list::render(&rows(), ListFormat::Csv).unwrap();
+    let records = parse_csv(&csv);
+    assert_eq!(
+        records[0],
+        ["version", "name", "status", "support", "released", "eol_date", "eol", "binary"]
+    );
+    assert_eq!(records.len(), 3);
+    assert!(records.iter().all(|record| record.len() == 8));
+    assert_eq!(
+        records[1],
+        [
+            "11.4",
+            "MariaDB Server 11.4",
+            "Stable",
+            "Long Term Support",
+            "-",
+            "2029-05-29",
+            "no",
+            "-"
+        ]
+    );
+    assert_eq!(records[2][1], "MariaDB Server 11.4.2, \"the second\"");
+    assert_eq!(records[2][7], "yes");
+}
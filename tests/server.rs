@@ -0,0 +1,179 @@
+//! Telling an instance's server from whatever else has its pid, and `server::start` against
+//! stand-in servers that never come up
+#![cfg(unix)]
+
+use std::path::Path;
+use std::time::Duration;
+
+use mariadb_version_manager_rs::instance::Instance;
+use mariadb_version_manager_rs::server;
+
+mod common;
+use common::scratch;
+
+/// An instance under `dir`, initialised as far as `start` can tell, with `server` as its
+/// `bin/mariadbd`
+fn instance(dir: &Path, server: &str) -> Instance {
+    use std::os::unix::fs::PermissionsExt;
+
+    let prefix = dir.join("bin-path");
+    std::fs::create_dir_all(prefix.join("bin")).unwrap();
+    let binary = prefix.join("bin").join("mariadbd");
+    std::fs::write(&binary, server).unwrap();
+    std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755)).unwrap();
+    std::fs::create_dir_all(dir.join("data").join("mysql")).unwrap();
+    Instance {
+        version: String::from("11.4.2"),
+        prefix,
+        data_path: dir.join("data"),
+        runtime_path: dir.join("run"),
+        logs_path: dir.join("logs"),
+        hostname: String::from("localhost"),
+        port: 3306,
+        database: String::from("database"),
+        locale: String::from("en_US.UTF-8"),
+        defaults_file: dir.join("my.cnf"),
+        includes: Vec::new(),
+    }
+}
+
+fn write_pid(instance: &Instance, pid: u32) {
+    std::fs::create_dir_all(&instance.runtime_path).unwrap();
+    std::fs::write(instance.pid_file(), format!("{}\n", pid)).unwrap();
+}
+
+/// Kills the stand-in server with the pid, and its process group, when the test is done
+/// with it
+struct Running(std::process::Child);
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        let _ = std::process::Command::new("kill")
+            .arg("--")
+            .arg(format!("-{}", self.0.id()))
+            .status();
+        let _ = self.0.wait();
+    }
+}
+
+#[test]
+fn rejects_a_pid_that_is_not_the_instance_server() {
+    let dir = scratch("server-reused-pid");
+    let instance = instance(&dir, "#!/bin/sh\n");
+
+    assert_eq!(server::running_pid(&instance), None);
+    // Gone
+    write_pid(&instance, u32::MAX - 1);
+    assert_eq!(server::running_pid(&instance), None);
+    // Running, but not a server
+    write_pid(&instance, std::process::id());
+    assert_eq!(server::running_pid(&instance), None);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn accepts_only_its_own_server_binary_and_defaults_file() {
+    let dir = scratch("server-own-pid");
+    let instance = instance(&dir, "");
+    // A copy of `sh`, as `/proc/<pid>/exe` names a script's interpreter, not the script
+    let shell = std::fs::read("/bin/sh").unwrap();
+    std::fs::write(instance.prefix.join("bin").join("mariadbd"), shell).unwrap();
+    let launch = |defaults_file_arg: String| {
+        let mut command = std::process::Command::new(instance.prefix.join("bin").join("mariadbd"));
+        // Not the last command, so `sh` does not `exec` it
+        command.args(["-c", "sleep 30; :"]).arg(&defaults_file_arg);
+        std::os::unix::process::CommandExt::process_group(&mut command, 0);
+        let running = Running(command.spawn().unwrap());
+        // Until the child has `exec`ed, it is a copy of this test
+        let cmdline = Path::new("/proc")
+            .join(running.0.id().to_string())
+            .join("cmdline");
+        let deadline = std::time::Instant::now() + Duration::from_secs(10);
+        while !std::fs::read(&cmdline)
+            .unwrap()
+            .split(|&byte| byte == 0)
+            .any(|arg| arg == defaults_file_arg.as_bytes())
+        {
+            assert!(std::time::Instant::now() < deadline, "not started");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        running
+    };
+
+    let server = launch(instance.defaults_file_arg());
+    write_pid(&instance, server.0.id());
+    assert_eq!(server::running_pid(&instance), Some(server.0.id()));
+
+    // The same binary, for another instance
+    let other = launch(format!(
+        "--defaults-file={}",
+        dir.join("other.cnf").display()
+    ));
+    write_pid(&instance, other.0.id());
+    assert_eq!(server::running_pid(&instance), None);
+}
+
+#[test]
+fn start_fails_when_the_server_exits() {
+    let dir = scratch("server-exits");
+    let instance = instance(
+        &dir,
+        "#!/bin/sh\necho \"[ERROR] unknown variable '$1'\" >&2\nexit 7\n",
+    );
+
+    let error = server::start(&instance, Duration::from_secs(10))
+        .unwrap_err()
+        .to_string();
+    assert!(
+        error.contains("mariadbd exited with exit status: 7"),
+        "{}",
+        error
+    );
+    assert!(error.contains("[ERROR] unknown variable '--defaults-file="));
+    assert!(!instance.pid_file().exists());
+    // `start` wrote the managed my.cnf for it
+    assert!(instance.defaults_file.is_file());
+}
+
+#[test]
+fn start_times_out_on_a_server_that_never_accepts_connections() {
+    let dir = scratch("server-timeout");
+    let instance = instance(&dir, "#!/bin/sh\nexec sleep 30\n");
+
+    let error = server::start(&instance, Duration::from_secs(1))
+        .unwrap_err()
+        .to_string();
+    let pid: u32 = std::fs::read_to_string(instance.pid_file())
+        .unwrap()
+        .trim()
+        .parse()
+        .unwrap();
+    let _ = std::process::Command::new("kill")
+        .arg(pid.to_string())
+        .status();
+    assert!(
+        error.contains(&format!(
+            "not accepting connections after 1 seconds, still running as pid {}",
+            pid
+        )),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn start_needs_an_initialised_data_directory() {
+    let dir = scratch("server-uninitialised");
+    let instance = instance(&dir, "#!/bin/sh\n");
+    std::fs::remove_dir(instance.data_path.join("mysql")).unwrap();
+
+    let error = server::start(&instance, Duration::from_secs(1))
+        .unwrap_err()
+        .to_string();
+    assert!(
+        error.ends_with("is not initialised; run `init` first"),
+        "{}",
+        error
+    );
+    assert!(!instance.pid_file().exists());
+}
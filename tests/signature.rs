@@ -0,0 +1,230 @@
+//! Verifying archives with `signature::verify_archive`, against a throwaway key made with `gpg`
+
+use std::path::{Path, PathBuf};
+
+use mariadb_version_manager_rs::signature::{self, SignatureOptions, SignatureSource};
+
+mod common;
+use common::{scratch, MockServer, Response};
+
+/// A signing key in its own `gpg` home, or `None` where there is no `gpg` to make one
+struct TestKey {
+    home: PathBuf,
+    fingerprint: String,
+    armored: String,
+}
+
+impl TestKey {
+    fn generate(dir: &Path) -> Option<Self> {
+        let home = dir.join("gnupg");
+        std::fs::create_dir_all(&home).unwrap();
+        #[cfg(unix)]
+        std::fs::set_permissions(&home, std::os::unix::fs::PermissionsExt::from_mode(0o700))
+            .unwrap();
+        let generated = gpg(
+            &home,
+            &[
+                "--passphrase",
+                "",
+                "--quick-gen-key",
+                "Test Signing Key <signing-key@example.com>",
+                "ed25519",
+                "sign",
+                "never",
+            ],
+        )?;
+        if !generated.status.success() {
+            return None;
+        }
+        let listed = gpg(&home, &["--with-colons", "--list-keys"])?;
+        let fingerprint = String::from_utf8_lossy(&listed.stdout)
+            .lines()
+            .find_map(|line| line.strip_prefix("fpr:"))?
+            .trim_matches(':')
+            .to_string();
+        let armored = String::from_utf8(gpg(&home, &["--armor", "--export"])?.stdout).ok()?;
+        Some(TestKey {
+            home,
+            fingerprint,
+            armored,
+        })
+    }
+
+    fn sign(&self, file: &Path) -> String {
+        let output = gpg(
+            &self.home,
+            &[
+                "--armor",
+                "--detach-sign",
+                "--output",
+                "-",
+                file.to_str().unwrap(),
+            ],
+        )
+        .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    }
+}
+
+impl Drop for TestKey {
+    fn drop(&mut self) {
+        let _ = std::process::Command::new("gpgconf")
+            .arg("--homedir")
+            .arg(&self.home)
+            .args(["--kill", "gpg-agent"])
+            .status();
+    }
+}
+
+fn gpg(home: &Path, args: &[&str]) -> Option<std::process::Output> {
+    std::process::Command::new("gpg")
+        .arg("--homedir")
+        .arg(home)
+        .arg("--batch")
+        .args(args)
+        .output()
+        .ok()
+}
+
+/// The archive, the key it is signed with, and a keyring holding that key
+fn signed_archive(name: &str) -> Option<(PathBuf, TestKey, PathBuf)> {
+    let dir = scratch(name);
+    let key = match TestKey::generate(&dir) {
+        Some(key) => key,
+        None => {
+            eprintln!("Skipped: no `gpg` to make a key with");
+            return None;
+        }
+    };
+    let keyring = signature::pinned_keyring(
+        &key.armored,
+        &key.fingerprint,
+        &dir.join("keys").join("test.gpg"),
+    )
+    .unwrap();
+    let archive = dir.join("mariadb-11.4.2-linux-x86_64.tar.gz");
+    std::fs::write(&archive, b"archive").unwrap();
+    Some((archive, key, keyring))
+}
+
+async fn verify(
+    archive: &Path,
+    source: Option<SignatureSource>,
+    keyring: Option<&Path>,
+    require_signature: bool,
+) -> Result<(), String> {
+    signature::verify_archive(
+        &reqwest::Client::new(),
+        archive,
+        source,
+        &SignatureOptions {
+            keyring: keyring.map(|keyring| keyring.as_os_str().to_os_string()),
+            require_signature,
+        },
+        &archive.with_file_name("keys"),
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+fn signature_file(archive: &Path) -> PathBuf {
+    archive.with_file_name("mariadb-11.4.2-linux-x86_64.tar.gz.asc")
+}
+
+#[test]
+fn pins_the_key_by_its_fingerprint() {
+    let Some((archive, key, keyring)) = signed_archive("signature-pin") else {
+        return;
+    };
+    // Binary, as `gpgv` wants it: an old-format public key packet
+    assert_eq!(std::fs::read(&keyring).unwrap()[0] & 0xfc, 0x98);
+
+    let other = "177F4010FE56CA3336300305F1656F24C74CD1D8";
+    let error =
+        signature::pinned_keyring(&key.armored, other, &archive.with_file_name("other.gpg"))
+            .unwrap_err()
+            .to_string();
+    assert!(
+        error.contains(&format!("not the OpenPGP key {}", other)),
+        "{}",
+        error
+    );
+    assert!(!archive.with_file_name("other.gpg").exists());
+}
+
+#[tokio::test]
+async fn accepts_a_good_signature() {
+    let Some((archive, key, keyring)) = signed_archive("signature-good") else {
+        return;
+    };
+    let signature = key.sign(&archive);
+    verify(
+        &archive,
+        Some(SignatureSource::Inline(signature)),
+        Some(&keyring),
+        true,
+    )
+    .await
+    .unwrap();
+    assert!(archive.is_file());
+    assert!(!signature_file(&archive).exists());
+
+    // Without `--keyring`, only the MariaDB release signing key will do
+    let signature = key.sign(&archive);
+    assert!(verify(
+        &archive,
+        Some(SignatureSource::Inline(signature)),
+        None,
+        true
+    )
+    .await
+    .is_err());
+}
+
+#[tokio::test]
+async fn rejects_and_removes_a_tampered_archive() {
+    let Some((archive, key, keyring)) = signed_archive("signature-bad") else {
+        return;
+    };
+    let signature = key.sign(&archive);
+    std::fs::write(&archive, b"tampered").unwrap();
+    // Not even a warning without `--require-signature`
+    let error = verify(
+        &archive,
+        Some(SignatureSource::Inline(signature)),
+        Some(&keyring),
+        false,
+    )
+    .await
+    .unwrap_err();
+    assert!(error.starts_with("Bad signature"), "{}", error);
+    assert!(!archive.exists());
+    assert!(!signature_file(&archive).exists());
+}
+
+#[tokio::test]
+async fn require_signature_fails_on_an_unverifiable_archive() {
+    let dir = scratch("signature-required");
+    let archive = dir.join("mariadb-11.4.2-linux-x86_64.tar.gz");
+    std::fs::write(&archive, b"archive").unwrap();
+    let server = MockServer::start(|_| Response::new(404, b""));
+    let url = || {
+        Some(SignatureSource::Url(
+            reqwest::Url::parse(&format!("{}/mariadb.tar.gz.asc", server.url)).unwrap(),
+        ))
+    };
+
+    verify(&archive, None, None, false).await.unwrap();
+    verify(&archive, url(), None, false).await.unwrap();
+    for source in [None, url()] {
+        let error = verify(&archive, source, None, true).await.unwrap_err();
+        assert!(
+            error.starts_with("Signature could not be verified"),
+            "{}",
+            error
+        );
+    }
+    // Unverified, but not shown to be bad
+    assert!(archive.is_file());
+}
@@ -0,0 +1,92 @@
+//! `Type=` of the unit `service install systemd` writes, which depends on whether the
+//! installed server binary can notify systemd
+
+use std::path::Path;
+
+use mariadb_version_manager_rs::instance::Instance;
+use mariadb_version_manager_rs::systemd;
+
+mod common;
+use common::scratch;
+
+fn instance(prefix: &Path) -> Instance {
+    Instance {
+        version: String::from("11.4.2"),
+        prefix: prefix.to_path_buf(),
+        data_path: prefix.join("data"),
+        runtime_path: Path::new("/run/mariadb").to_path_buf(),
+        logs_path: prefix.join("logs"),
+        hostname: String::from("localhost"),
+        port: 3306,
+        database: String::from("database"),
+        locale: String::from("en_US.UTF-8"),
+        defaults_file: prefix.join("my.cnf"),
+        includes: Vec::new(),
+    }
+}
+
+fn service_type(name: &str, server_binary: Option<&[u8]>) -> String {
+    let prefix = scratch(name);
+    if let Some(server_binary) = server_binary {
+        std::fs::create_dir_all(prefix.join("bin")).unwrap();
+        std::fs::write(prefix.join("bin").join("mariadbd"), server_binary).unwrap();
+    }
+    let options = systemd::UnitOptions {
+        user: String::from("mysql"),
+        group: String::from("mysql"),
+        user_unit: false,
+        template_version: None,
+    };
+    let unit = systemd::render(&instance(&prefix), &options).unwrap();
+    unit.lines()
+        .find_map(|line| line.strip_prefix("Type="))
+        .unwrap()
+        .to_string()
+}
+
+/// `sd_notify` at `offset` of a binary of `len` bytes
+fn binary_with_sd_notify(len: usize, offset: usize) -> Vec<u8> {
+    let mut binary = vec![0x7f_u8; len];
+    binary[offset..offset + b"sd_notify".len()].copy_from_slice(b"sd_notify");
+    binary
+}
+
+#[test]
+fn notifies_when_linked_with_libsystemd() {
+    assert_eq!(
+        service_type(
+            "systemd-notify",
+            Some(&binary_with_sd_notify(100_000, 50_000))
+        ),
+        "notify"
+    );
+}
+
+#[test]
+fn finds_sd_notify_across_read_buffers() {
+    // `BufReader` reads 8 KiB at a time
+    for offset in [8192 - 4, 8192 - 1, 8192, 3 * 8192 - 8] {
+        assert_eq!(
+            service_type(
+                &format!("systemd-notify-{}", offset),
+                Some(&binary_with_sd_notify(5 * 8192, offset))
+            ),
+            "notify",
+            "at {}",
+            offset
+        );
+    }
+}
+
+#[test]
+fn simple_without_libsystemd() {
+    assert_eq!(
+        service_type("systemd-simple", Some(&vec![0x7f_u8; 100_000])),
+        "simple"
+    );
+}
+
+#[test]
+fn notifies_when_not_installed_yet() {
+    assert_eq!(service_type("systemd-not-installed", None), "notify");
+}
@@ -0,0 +1,129 @@
+//! Ordering of `MariaDbVersion` and what `VersionReq` and `VersionSelector` accept
+
+use mariadb_version_manager_rs::version::{MariaDbVersion, VersionReq, VersionSelector};
+
+fn version(s: &str) -> MariaDbVersion {
+    s.parse().unwrap()
+}
+
+fn req(s: &str) -> VersionReq {
+    s.parse().unwrap()
+}
+
+#[test]
+fn orders_numerically() {
+    assert!(version("10.11") > version("10.6"));
+    assert!(version("10.6.10") > version("10.6.9"));
+    assert!(version("11.0.0") > version("10.11.8"));
+}
+
+#[test]
+fn orders_pre_releases_before_their_release() {
+    assert!(version("11.0.0-rc") < version("11.0.0"));
+    assert!(version("11.0.0-rc") > version("10.11.8"));
+    assert_eq!(version("11.0.0-RC"), version("11.0.0-rc"));
+}
+
+#[test]
+fn rejects_malformed_versions() {
+    for s in ["", "10", "10.x", "10.6.1.2", "10.6-", "ten.six"] {
+        assert!(s.parse::<MariaDbVersion>().is_err(), "{:?}", s);
+    }
+}
+
+#[test]
+fn wildcard_matches_the_series() {
+    let req = req("10.11.x");
+    assert!(req.matches(&version("10.11.0")));
+    assert!(req.matches(&version("10.11.8")));
+    assert!(!req.matches(&version("10.1.48")));
+    assert!(!req.matches(&version("11.0.0")));
+    assert_eq!(req.exact(), None);
+    assert_eq!(req, VersionReq::series(&version("10.11")));
+}
+
+#[test]
+fn comparators_intersect() {
+    let req = req(">=10.6 <11");
+    assert!(req.matches(&version("10.6.0")));
+    assert!(req.matches(&version("10.11.8")));
+    assert!(!req.matches(&version("10.5.25")));
+    assert!(!req.matches(&version("11.0.0")));
+    assert_eq!(req, ">= 10.6, < 11".parse().unwrap());
+    assert!(req.matches_series(&version("10.11")));
+    assert!(!req.matches_series(&version("11.4")));
+}
+
+#[test]
+fn tilde_allows_patch_releases() {
+    let req = req("~11.4");
+    assert!(req.matches(&version("11.4.0")));
+    assert!(req.matches(&version("11.4.3")));
+    assert!(!req.matches(&version("11.5.0")));
+    assert!(!req.matches(&version("11.3.2")));
+}
+
+#[test]
+fn caret_allows_the_major_version() {
+    let req = req("^10");
+    assert!(req.matches(&version("10.0.0")));
+    assert!(req.matches(&version("10.11.8")));
+    assert!(!req.matches(&version("11.0.0")));
+    assert!(!req.matches(&version("5.5.68")));
+}
+
+#[test]
+fn a_point_release_is_exact() {
+    let req = req("10.6.9");
+    assert_eq!(req.exact(), Some(version("10.6.9")));
+    assert!(req.matches(&version("10.6.9")));
+    assert!(!req.matches(&version("10.6.10")));
+}
+
+#[test]
+fn an_exact_pre_release_is_accepted() {
+    let req = req("11.5.0-rc");
+    assert_eq!(req.exact(), Some(version("11.5.0-rc")));
+    assert!(req.matches(&version("11.5.0-rc")));
+    assert!(!req.matches(&version("11.5.0")));
+    assert_eq!("=11.5.0-rc".parse::<VersionReq>().unwrap(), req);
+}
+
+#[test]
+fn pre_releases_only_as_a_single_exact_version() {
+    for s in [">=11.5.0-rc", "11.5-rc", "11.5.0-rc <12", "11.5.0-"] {
+        assert!(s.parse::<VersionReq>().is_err(), "{:?}", s);
+    }
+}
+
+#[test]
+fn rejects_malformed_requirements() {
+    for s in ["", ">=", "10.6 <", "!10.6", "10.6.1.2", "lts"] {
+        assert!(s.parse::<VersionReq>().is_err(), "{:?}", s);
+    }
+}
+
+#[test]
+fn selectors() {
+    assert_eq!(
+        "lts".parse::<VersionSelector>().unwrap(),
+        VersionSelector::Lts(0)
+    );
+    assert_eq!(
+        "LTS-1".parse::<VersionSelector>().unwrap(),
+        VersionSelector::Lts(1)
+    );
+    assert_eq!(
+        "latest".parse::<VersionSelector>().unwrap(),
+        VersionSelector::Stable
+    );
+    assert_eq!(
+        "rc".parse::<VersionSelector>().unwrap(),
+        VersionSelector::Rc
+    );
+    assert_eq!(
+        "~11.4".parse::<VersionSelector>().unwrap(),
+        VersionSelector::Range(req("~11.4"))
+    );
+    assert!("lts-x".parse::<VersionSelector>().is_err());
+}